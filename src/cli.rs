@@ -0,0 +1,248 @@
+use crate::robot::{check_loadout, RobotModule};
+use crate::simulation::recorder::MetricsFormat;
+use crate::simulation::scenario::{
+    RobotGroup, Scenario, ScenarioError, MAX_MAP_SIDE, MIN_MAP_SIDE,
+};
+use crate::station::build::BuildPlan;
+use crate::station::tasks::Allocation;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use std::path::PathBuf;
+
+/// Swarm of robots exploring a procedurally generated planet.
+#[derive(Debug, Parser)]
+#[command(name = "ereea", version)]
+pub struct Cli {
+    /// Scenario file, in TOML or JSON, to start from. The options below
+    /// override what it sets.
+    #[arg(long, env = "EREEA_SCENARIO")]
+    pub scenario: Option<PathBuf>,
+
+    /// Seed of the map and of every random decision [default: 42].
+    #[arg(long)]
+    pub seed: Option<u32>,
+
+    /// Map width, in cells [default: 50].
+    #[arg(long, value_parser = parse_map_side)]
+    pub width: Option<usize>,
+
+    /// Map height, in cells [default: 30].
+    #[arg(long, value_parser = parse_map_side)]
+    pub height: Option<usize>,
+
+    /// Steps to run before stopping [default: 1000].
+    #[arg(long, value_parser = parse_positive)]
+    pub steps: Option<usize>,
+
+    /// Milliseconds between two steps in the terminal view.
+    #[arg(long, default_value_t = 100)]
+    pub tick_ms: u64,
+
+    /// Robots to start with, separated by commas. Each robot is a list of
+    /// modules joined by '+', e.g. "exploration,drill+energy-collector"
+    /// [default: exploration,exploration,drill,drill,energy-collector].
+    #[arg(long, value_delimiter = ',', value_parser = parse_robot)]
+    pub fleet: Option<Vec<Vec<RobotModule>>>,
+
+    /// Run without the terminal view, as fast as possible, and print the
+    /// final stats.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Runs to make, on consecutive seeds from --seed. More than one run
+    /// implies --headless.
+    #[arg(long, env = "EREEA_BATCH", default_value_t = 1, value_parser = parse_positive)]
+    pub runs: usize,
+
+    /// Threads to spread runs over. Defaults to one per core.
+    #[arg(long, env = "EREEA_BATCH_THREADS", value_parser = parse_positive)]
+    pub threads: Option<usize>,
+
+    /// File to write the final stats of every headless run into, as .csv or
+    /// .jsonl.
+    #[arg(long, env = "EREEA_BATCH_OUTPUT")]
+    pub summary: Option<PathBuf>,
+
+    /// File to record metrics into during a single run, as .csv or .jsonl.
+    #[arg(long, env = "EREEA_METRICS")]
+    pub metrics: Option<PathBuf>,
+
+    /// Steps between two metrics snapshots.
+    #[arg(long, env = "EREEA_METRICS_INTERVAL", default_value_t = 10, value_parser = parse_positive)]
+    pub metrics_interval: usize,
+
+    /// JSON file of behaviour trees to use instead of the bundled ones.
+    #[arg(long, env = "EREEA_BEHAVIOR_TREES")]
+    pub behavior_trees: Option<PathBuf>,
+
+    /// How tasks are allocated: "greedy" or "contract-net" [default:
+    /// greedy].
+    #[arg(long, env = "EREEA_ALLOCATION")]
+    pub allocation: Option<Allocation>,
+
+    /// How stations pick the robots they build: "heuristic", "fixed-ratio"
+    /// or "demand-driven". Scripted schedules are set in a scenario file
+    /// [default: heuristic].
+    #[arg(long, env = "EREEA_BUILD_PLAN")]
+    pub build_plan: Option<BuildPlan>,
+}
+
+impl Cli {
+    /// Parses the command line, exiting with a usage message when it does
+    /// not make sense.
+    pub fn parse_and_check() -> Self {
+        let cli = Self::parse();
+        for (flag, path) in [("--metrics", &cli.metrics), ("--summary", &cli.summary)] {
+            if let Some(path) = path.as_deref() {
+                if MetricsFormat::from_path(path).is_none() {
+                    Self::command()
+                        .error(
+                            ErrorKind::InvalidValue,
+                            format!(
+                                "cannot tell the format of {} {}, use a .csv or .jsonl file",
+                                flag,
+                                path.display()
+                            ),
+                        )
+                        .exit();
+                }
+            }
+        }
+        if cli.metrics.is_some() && cli.runs > 1 {
+            Self::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--metrics records a single run; use --summary to keep the results of several",
+                )
+                .exit();
+        }
+        cli
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.headless || self.runs > 1
+    }
+
+    /// The mission to run: the scenario file if one was given, or the stock
+    /// one, with the options given on the command line applied over it.
+    pub fn scenario(&self) -> Result<Scenario, ScenarioError> {
+        let mut scenario = match &self.scenario {
+            Some(path) => Scenario::load(path)?,
+            None => Scenario::default(),
+        };
+
+        if let Some(seed) = self.seed {
+            scenario.seed = seed;
+        }
+        if let Some(width) = self.width {
+            scenario.map.width = width;
+        }
+        if let Some(height) = self.height {
+            scenario.map.height = height;
+        }
+        if let Some(steps) = self.steps {
+            scenario.steps = steps;
+        }
+        if let Some(plan) = &self.build_plan {
+            scenario.station.build_plan = plan.clone();
+        }
+        if let Some(allocation) = self.allocation {
+            scenario.station.allocation = allocation;
+        }
+        if let Some(fleet) = &self.fleet {
+            scenario.robots = fleet
+                .iter()
+                .map(|modules| RobotGroup {
+                    modules: modules.clone(),
+                    count: 1,
+                    station: 0,
+                })
+                .collect();
+        }
+
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Seeds of every run asked for, counting up from `first`.
+    pub fn seeds(&self, first: u32) -> Vec<u32> {
+        (0..self.runs as u32)
+            .map(|run| first.wrapping_add(run))
+            .collect()
+    }
+}
+
+fn parse_map_side(value: &str) -> Result<usize, String> {
+    let side: usize = value
+        .parse()
+        .map_err(|_| format!("\"{}\" is not a whole number of cells", value))?;
+    if !(MIN_MAP_SIDE..=MAX_MAP_SIDE).contains(&side) {
+        return Err(format!(
+            "maps are between {} and {} cells a side",
+            MIN_MAP_SIDE, MAX_MAP_SIDE
+        ));
+    }
+    Ok(side)
+}
+
+fn parse_positive(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(number) => Ok(number),
+        Err(_) => Err(format!("\"{}\" is not a whole number", value)),
+    }
+}
+
+fn parse_robot(value: &str) -> Result<Vec<RobotModule>, String> {
+    let modules = value
+        .split('+')
+        .map(str::parse)
+        .collect::<Result<Vec<RobotModule>, String>>()?;
+    check_loadout(&modules)?;
+    Ok(modules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fleets_and_bounds_are_checked() {
+        let cli = Cli::try_parse_from([
+            "ereea",
+            "--fleet",
+            "drill+energy-collector,exploration",
+            "--width",
+            "60",
+        ])
+        .unwrap();
+        let scenario = cli.scenario().unwrap();
+        assert_eq!(
+            scenario.fleet(0),
+            vec![
+                vec![RobotModule::Drill, RobotModule::EnergyCollector],
+                vec![RobotModule::Exploration],
+            ]
+        );
+        assert_eq!((scenario.map.width, scenario.map.height), (60, 30));
+        assert_eq!(scenario.station.allocation, Allocation::Greedy);
+
+        let cli = Cli::try_parse_from(["ereea", "--allocation", "contract-net"]).unwrap();
+        assert_eq!(
+            cli.scenario().unwrap().station.allocation,
+            Allocation::ContractNet
+        );
+
+        for bad in [
+            ["ereea", "--fleet", "drill+drill"],
+            ["ereea", "--fleet", "drill+energy-collector+comms-relay"],
+            ["ereea", "--fleet", "laser"],
+            ["ereea", "--width", "5"],
+            ["ereea", "--steps", "0"],
+            ["ereea", "--allocation", "auction"],
+            ["ereea", "--build-plan", "scripted"],
+        ] {
+            assert!(Cli::try_parse_from(bad).is_err(), "{:?}", bad);
+        }
+    }
+}
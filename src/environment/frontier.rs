@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+
+use super::map::CellType;
+use super::KnowledgeMap;
+
+/// Furthest a cluster reaches from its first cell, in cells. Keeps the
+/// frontier ringing the known area from merging into one cluster whose
+/// middle sits back inside explored ground.
+const CLUSTER_REACH: usize = 4;
+
+/// How far past a frontier cell a visit reveals unknown ground, about a
+/// robot's sensor radius.
+const GAIN_REACH: usize = 2;
+
+/// A known, walkable cell with unknown ground next to it.
+pub fn is_frontier(knowledge: &KnowledgeMap, x: usize, y: usize) -> bool {
+    let walkable = knowledge
+        .cell(x, y)
+        .is_some_and(|cell| cell != CellType::Obstacle);
+    if !walkable {
+        return false;
+    }
+
+    let neighbours = [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ];
+    neighbours.into_iter().any(|(nx, ny)| {
+        nx < knowledge.width && ny < knowledge.height && !knowledge.is_known(nx, ny)
+    })
+}
+
+/// Frontier cells touching one another, explored as one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontierCluster {
+    pub cells: Vec<(usize, usize)>,
+    /// Cell of the cluster closest to its middle, where robots are sent.
+    pub target: (usize, usize),
+    /// Unknown cells within `GAIN_REACH` of the cluster.
+    unknown_nearby: usize,
+}
+
+impl FrontierCluster {
+    /// How much a visit would reveal. Counting the unknown ground around the
+    /// cluster rather than its cells keeps a thin strip along the map edge
+    /// from looking as promising as open ground.
+    pub fn information_gain(&self) -> usize {
+        self.unknown_nearby
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.cells.contains(&(x, y))
+    }
+}
+
+/// Groups the frontier cells of `knowledge` into 8-connected clusters of
+/// bounded size, in row-major order of their first cell.
+pub fn frontier_clusters(knowledge: &KnowledgeMap) -> Vec<FrontierCluster> {
+    let mut visited = vec![vec![false; knowledge.width]; knowledge.height];
+    let mut clusters = Vec::new();
+
+    for y in 0..knowledge.height {
+        for x in 0..knowledge.width {
+            if visited[y][x] || !is_frontier(knowledge, x, y) {
+                continue;
+            }
+
+            visited[y][x] = true;
+            let mut cells = Vec::new();
+            let mut pending = vec![(x, y)];
+            while let Some((cx, cy)) = pending.pop() {
+                cells.push((cx, cy));
+                let rows = cy.saturating_sub(1)..=(cy + 1).min(knowledge.height - 1);
+                let neighbours = rows.flat_map(|ny| {
+                    (cx.saturating_sub(1)..=(cx + 1).min(knowledge.width - 1))
+                        .map(move |nx| (nx, ny))
+                });
+                for (nx, ny) in neighbours {
+                    let within_reach =
+                        nx.abs_diff(x) <= CLUSTER_REACH && ny.abs_diff(y) <= CLUSTER_REACH;
+                    if within_reach && !visited[ny][nx] && is_frontier(knowledge, nx, ny) {
+                        visited[ny][nx] = true;
+                        pending.push((nx, ny));
+                    }
+                }
+            }
+
+            cells.sort_unstable_by_key(|&(cx, cy)| (cy, cx));
+            let count = cells.len();
+            let mean_x = cells.iter().map(|&(cx, _)| cx).sum::<usize>() / count;
+            let mean_y = cells.iter().map(|&(_, cy)| cy).sum::<usize>() / count;
+            let target = *cells
+                .iter()
+                .min_by_key(|&&(cx, cy)| cx.abs_diff(mean_x).pow(2) + cy.abs_diff(mean_y).pow(2))
+                .unwrap_or(&(x, y));
+
+            let unknown_nearby = unknown_nearby(knowledge, &cells);
+            clusters.push(FrontierCluster {
+                cells,
+                target,
+                unknown_nearby,
+            });
+        }
+    }
+
+    clusters
+}
+
+/// Unknown cells within `GAIN_REACH` of any of `cells`, each counted once.
+fn unknown_nearby(knowledge: &KnowledgeMap, cells: &[(usize, usize)]) -> usize {
+    let mut unknown = HashSet::new();
+    for &(x, y) in cells {
+        for ny in y.saturating_sub(GAIN_REACH)..=(y + GAIN_REACH).min(knowledge.height - 1) {
+            for nx in x.saturating_sub(GAIN_REACH)..=(x + GAIN_REACH).min(knowledge.width - 1) {
+                if !knowledge.is_known(nx, ny) {
+                    unknown.insert((nx, ny));
+                }
+            }
+        }
+    }
+    unknown.len()
+}
+
+/// The cluster worth the most per step travelled, given the cost of the path
+/// to every cell in `distances`. Unreachable clusters and those `skip` rejects
+/// are left out.
+pub fn best_frontier<'a>(
+    clusters: &'a [FrontierCluster],
+    distances: &[Vec<Option<u32>>],
+    skip: impl Fn(&FrontierCluster) -> bool,
+) -> Option<&'a FrontierCluster> {
+    clusters
+        .iter()
+        .filter(|cluster| !skip(cluster))
+        .filter_map(|cluster| {
+            let (x, y) = cluster.target;
+            let steps = distances[y][x]?;
+            Some((
+                cluster,
+                cluster.information_gain() as f32 / (steps + 1) as f32,
+            ))
+        })
+        .fold(
+            None,
+            |best: Option<(&FrontierCluster, f32)>, (cluster, score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((cluster, score)),
+            },
+        )
+        .map(|(cluster, _)| cluster)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontiers_are_clustered_and_scored_by_gain_over_distance() {
+        // Two strips of known ground, separated by unknown cells.
+        let mut knowledge = KnowledgeMap::new(12, 3);
+        for x in 0..3 {
+            knowledge.observe(x, 0, CellType::Empty, 0);
+        }
+        for x in 6..11 {
+            knowledge.observe(x, 0, CellType::Empty, 0);
+        }
+        knowledge.observe(9, 2, CellType::Obstacle, 0);
+
+        let clusters = frontier_clusters(&knowledge);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].information_gain(), 12);
+        assert_eq!(clusters[0].target, (1, 0));
+        assert_eq!(clusters[1].information_gain(), 18);
+        assert!(
+            !is_frontier(&knowledge, 9, 2),
+            "obstacles are not frontiers"
+        );
+
+        let mut distances = vec![vec![None; 12]; 3];
+        distances[0][1] = Some(1);
+        distances[0][8] = Some(7);
+        assert_eq!(
+            best_frontier(&clusters, &distances, |_| false),
+            Some(&clusters[0])
+        );
+
+        distances[0][8] = Some(1);
+        assert_eq!(
+            best_frontier(&clusters, &distances, |_| false),
+            Some(&clusters[1])
+        );
+        assert_eq!(
+            best_frontier(&clusters, &distances, |c| c.contains(8, 0)),
+            Some(&clusters[0])
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use super::map::{CellType, Map, SiteKind};
+
+/// What an observer saw in a cell, and when.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellObservation {
+    pub cell: CellType,
+    /// Kind of the scientific site in the cell, seen along with it.
+    pub site: Option<SiteKind>,
+    pub observed_at: usize,
+}
+
+/// A partial, timestamped copy of the map as seen by a robot or the station.
+///
+/// Robots only ever see their own observations until they sync with the
+/// station, which keeps the master copy.
+#[derive(Debug, Clone)]
+pub struct KnowledgeMap {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Vec<Option<CellObservation>>>,
+}
+
+/// How a merge settled a cell both sides disagreed on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictResolution {
+    /// One side saw a resource or site the other saw exhausted. They never
+    /// reappear, so the exhausted state wins whatever the timestamps say.
+    ResourceDepleted,
+    /// The incoming observation is more recent and replaces ours.
+    TookIncoming,
+    /// Our observation is at least as recent and is kept.
+    KeptExisting,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeConflict {
+    pub x: usize,
+    pub y: usize,
+    pub existing: CellObservation,
+    pub incoming: CellObservation,
+    pub resolution: ConflictResolution,
+}
+
+impl ConflictResolution {
+    pub const ALL: [ConflictResolution; 3] = [
+        ConflictResolution::ResourceDepleted,
+        ConflictResolution::TookIncoming,
+        ConflictResolution::KeptExisting,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictResolution::ResourceDepleted => "depleted",
+            ConflictResolution::TookIncoming => "took robot",
+            ConflictResolution::KeptExisting => "kept station",
+        }
+    }
+}
+
+/// Summary of a merge: cells learned, cells refreshed and conflicts settled.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeOutcome {
+    pub added: usize,
+    pub updated: usize,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeOutcome {
+    /// Number of conflicts settled with `resolution`.
+    pub fn resolution_count(&self, resolution: ConflictResolution) -> usize {
+        self.conflicts
+            .iter()
+            .filter(|c| c.resolution == resolution)
+            .count()
+    }
+}
+
+/// Commit-like record of a robot pushing its knowledge to the station.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KnowledgeCommit {
+    pub id: usize,
+    pub robot_id: usize,
+    pub step: usize,
+    pub outcome: MergeOutcome,
+}
+
+impl KnowledgeMap {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![vec![None; width]; height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&CellObservation> {
+        self.cells.get(y)?.get(x)?.as_ref()
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<CellType> {
+        self.get(x, y).map(|obs| obs.cell)
+    }
+
+    pub fn site_kind(&self, x: usize, y: usize) -> Option<SiteKind> {
+        self.get(x, y)?.site
+    }
+
+    pub fn is_known(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).is_some()
+    }
+
+    /// Share of the map's cells that have been observed at least once.
+    pub fn known_fraction(&self) -> f32 {
+        let known = self.cells.iter().flatten().filter(|c| c.is_some()).count();
+        known as f32 / (self.width * self.height).max(1) as f32
+    }
+
+    /// Records `cell` at (`x`, `y`). Returns true when the observation taught
+    /// us something, i.e. the cell was unknown or its content changed.
+    pub fn observe(&mut self, x: usize, y: usize, cell: CellType, step: usize) -> bool {
+        self.record(x, y, cell, None, step)
+    }
+
+    /// Records a scientific site of `kind` at (`x`, `y`).
+    pub fn observe_site(&mut self, x: usize, y: usize, kind: SiteKind, step: usize) -> bool {
+        self.record(x, y, CellType::ScientificSite, Some(kind), step)
+    }
+
+    fn record(
+        &mut self,
+        x: usize,
+        y: usize,
+        cell: CellType,
+        site: Option<SiteKind>,
+        step: usize,
+    ) -> bool {
+        let slot = &mut self.cells[y][x];
+        let changed = slot.is_none_or(|obs| obs.cell != cell || obs.site != site);
+        *slot = Some(CellObservation {
+            cell,
+            site,
+            observed_at: step,
+        });
+        changed
+    }
+
+    /// Observes every cell of `map` within `radius` of (`x`, `y`).
+    pub fn observe_area(
+        &mut self,
+        map: &Map,
+        x: usize,
+        y: usize,
+        radius: i32,
+        step: usize,
+    ) -> bool {
+        let mut changed = false;
+
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let new_x = x as i32 + dx;
+                let new_y = y as i32 + dy;
+
+                if new_x >= 0
+                    && new_x < self.width as i32
+                    && new_y >= 0
+                    && new_y < self.height as i32
+                {
+                    let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                    if distance <= radius as f32 {
+                        let nx = new_x as usize;
+                        let ny = new_y as usize;
+                        changed |= match map.site_kinds[ny][nx] {
+                            Some(kind) => self.observe_site(nx, ny, kind, step),
+                            None => self.observe(nx, ny, map.cells[ny][nx], step),
+                        };
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Merges `incoming` into this map, git style: unknown cells are added,
+    /// newer identical observations refresh ours, and differing observations
+    /// are settled explicitly and reported as conflicts.
+    pub fn merge(&mut self, incoming: &KnowledgeMap) -> MergeOutcome {
+        let mut outcome = MergeOutcome::default();
+
+        for y in 0..self.height.min(incoming.height) {
+            for x in 0..self.width.min(incoming.width) {
+                let Some(theirs) = incoming.cells[y][x] else {
+                    continue;
+                };
+
+                let slot = &mut self.cells[y][x];
+                match *slot {
+                    None => {
+                        *slot = Some(theirs);
+                        outcome.added += 1;
+                    }
+                    Some(ours) if ours.cell == theirs.cell => {
+                        if theirs.observed_at > ours.observed_at {
+                            *slot = Some(theirs);
+                            outcome.updated += 1;
+                        }
+                    }
+                    Some(ours) => {
+                        let resolution = resolve_conflict(&ours, &theirs);
+                        let take_incoming = match resolution {
+                            ConflictResolution::ResourceDepleted => theirs.cell == CellType::Empty,
+                            ConflictResolution::TookIncoming => true,
+                            ConflictResolution::KeptExisting => false,
+                        };
+                        if take_incoming {
+                            *slot = Some(theirs);
+                        }
+                        outcome.conflicts.push(MergeConflict {
+                            x,
+                            y,
+                            existing: ours,
+                            incoming: theirs,
+                            resolution,
+                        });
+                    }
+                }
+            }
+        }
+
+        outcome
+    }
+}
+
+/// Cells that turn empty once used up and never come back.
+fn is_consumable(cell: CellType) -> bool {
+    matches!(
+        cell,
+        CellType::Energy | CellType::Mineral | CellType::ScientificSite
+    )
+}
+
+fn resolve_conflict(existing: &CellObservation, incoming: &CellObservation) -> ConflictResolution {
+    let depleted = (is_consumable(existing.cell) && incoming.cell == CellType::Empty)
+        || (existing.cell == CellType::Empty && is_consumable(incoming.cell));
+
+    if depleted {
+        ConflictResolution::ResourceDepleted
+    } else if incoming.observed_at > existing.observed_at {
+        ConflictResolution::TookIncoming
+    } else {
+        ConflictResolution::KeptExisting
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_adds_unknown_cells_and_refreshes_newer_ones() {
+        let mut station = KnowledgeMap::new(3, 1);
+        station.observe(0, 0, CellType::Empty, 1);
+
+        let mut robot = KnowledgeMap::new(3, 1);
+        robot.observe(0, 0, CellType::Empty, 5);
+        robot.observe(1, 0, CellType::Obstacle, 5);
+
+        let outcome = station.merge(&robot);
+
+        assert_eq!(outcome.added, 1);
+        assert_eq!(outcome.updated, 1);
+        assert!(outcome.conflicts.is_empty());
+        assert_eq!(station.get(0, 0).unwrap().observed_at, 5);
+        assert_eq!(station.cell(1, 0), Some(CellType::Obstacle));
+        assert!(!station.is_known(2, 0));
+    }
+
+    #[test]
+    fn harvested_state_wins_resource_conflicts() {
+        let mut station = KnowledgeMap::new(1, 1);
+        station.observe(0, 0, CellType::Empty, 3);
+
+        let mut robot = KnowledgeMap::new(1, 1);
+        robot.observe(0, 0, CellType::Mineral, 1);
+
+        let outcome = station.merge(&robot);
+
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(
+            outcome.conflicts[0].resolution,
+            ConflictResolution::ResourceDepleted
+        );
+        assert_eq!(station.cell(0, 0), Some(CellType::Empty));
+
+        let mut stale_station = KnowledgeMap::new(1, 1);
+        stale_station.observe(0, 0, CellType::Energy, 1);
+        let mut harvester = KnowledgeMap::new(1, 1);
+        harvester.observe(0, 0, CellType::Empty, 4);
+
+        stale_station.merge(&harvester);
+        assert_eq!(stale_station.cell(0, 0), Some(CellType::Empty));
+    }
+
+    #[test]
+    fn other_conflicts_keep_the_newest_observation() {
+        let mut station = KnowledgeMap::new(1, 1);
+        station.observe(0, 0, CellType::ScientificSite, 2);
+
+        let mut older = KnowledgeMap::new(1, 1);
+        older.observe(0, 0, CellType::Obstacle, 1);
+        let outcome = station.merge(&older);
+        assert_eq!(
+            outcome.conflicts[0].resolution,
+            ConflictResolution::KeptExisting
+        );
+        assert_eq!(station.cell(0, 0), Some(CellType::ScientificSite));
+
+        let mut newer = KnowledgeMap::new(1, 1);
+        newer.observe(0, 0, CellType::Obstacle, 9);
+        let outcome = station.merge(&newer);
+        assert_eq!(
+            outcome.conflicts[0].resolution,
+            ConflictResolution::TookIncoming
+        );
+        assert_eq!(station.cell(0, 0), Some(CellType::Obstacle));
+    }
+}
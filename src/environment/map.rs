@@ -1,5 +1,18 @@
+use log::warn;
 use noise::{NoiseFn, Perlin};
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+/// Smallest and largest number of units an energy or mineral deposit holds.
+pub const MIN_DEPOSIT: u32 = 2;
+pub const MAX_DEPOSIT: u32 = 10;
+/// Share of the map a station must reach on foot for its cell to be kept.
+const MIN_STATION_REACH: f32 = 0.1;
+/// Random cells tried for each deposit or site before settling for the
+/// richest free one among them.
+const PLACEMENT_ATTEMPTS: usize = 30;
 
 #[derive(Debug, Clone)]
 pub struct MapConfig {
@@ -8,6 +21,86 @@ pub struct MapConfig {
     pub seed: u32,
 }
 
+impl MapConfig {
+    /// Cell in the middle of the map, where the station stands unless told
+    /// otherwise.
+    pub fn centre(&self) -> (usize, usize) {
+        (self.width / 2, self.height / 2)
+    }
+}
+
+/// Shape of the terrain noise. The defaults give the stock cave network.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerrainParams {
+    /// Frequency of the terrain noise; higher gives smaller features.
+    pub scale: f64,
+    /// Noise value, in [-1, 1], above which a cell starts out as rock.
+    pub obstacle_threshold: f64,
+    /// Smoothing passes turning the raw noise into caves.
+    pub smoothing_passes: usize,
+    /// Frequency of the noise placing and sizing deposits; lower gives wider
+    /// rich areas.
+    pub richness_scale: f64,
+}
+
+impl Default for TerrainParams {
+    fn default() -> Self {
+        Self {
+            scale: 0.15,
+            obstacle_threshold: 0.2,
+            smoothing_passes: 4,
+            richness_scale: 0.08,
+        }
+    }
+}
+
+/// How many deposits of one kind to scatter: a fixed count, or a share of
+/// the map's cells.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Abundance {
+    pub count: Option<usize>,
+    /// Deposits per cell of map, in [0, 1].
+    pub density: Option<f64>,
+}
+
+impl Abundance {
+    pub fn count(count: usize) -> Self {
+        Self {
+            count: Some(count),
+            density: None,
+        }
+    }
+
+    /// Deposits to place on a map of `cells` cells.
+    pub fn resolve(&self, cells: usize) -> usize {
+        match (self.count, self.density) {
+            (Some(count), _) => count,
+            (None, Some(density)) => (density * cells as f64).round() as usize,
+            (None, None) => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceParams {
+    pub energy: Abundance,
+    pub minerals: Abundance,
+    pub science_sites: Abundance,
+}
+
+impl Default for ResourceParams {
+    fn default() -> Self {
+        Self {
+            energy: Abundance::count(20),
+            minerals: Abundance::count(20),
+            science_sites: Abundance::count(5),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CellType {
     Empty,
@@ -17,11 +110,52 @@ pub enum CellType {
     ScientificSite,
 }
 
+/// What a scientific site can teach us. Each kind yields a finite number
+/// of samples, each taking a while to collect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SiteKind {
+    Geological,
+    Chemical,
+    Biosignature,
+}
+
+impl SiteKind {
+    pub const ALL: [SiteKind; 3] = [
+        SiteKind::Geological,
+        SiteKind::Chemical,
+        SiteKind::Biosignature,
+    ];
+
+    /// Samples a fresh site of this kind can give before it is exhausted.
+    pub fn yield_budget(&self) -> u32 {
+        match self {
+            SiteKind::Geological => 6,
+            SiteKind::Chemical => 4,
+            SiteKind::Biosignature => 2,
+        }
+    }
+
+    /// Ticks a robot spends on the site to take one sample.
+    pub fn sampling_ticks(&self) -> u32 {
+        match self {
+            SiteKind::Geological => 4,
+            SiteKind::Chemical => 3,
+            SiteKind::Biosignature => 6,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Map {
     pub config: MapConfig,
     pub cells: Vec<Vec<CellType>>,
+    /// Units left in each energy or mineral deposit, or samples left at each
+    /// scientific site; zero everywhere else.
+    pub amounts: Vec<Vec<u32>>,
+    pub site_kinds: Vec<Vec<Option<SiteKind>>>,
     pub visibility: Vec<Vec<CellVisibility>>,
+    /// Cells the stations stand on, once settled on open ground.
+    pub bases: Vec<(usize, usize)>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -32,46 +166,89 @@ pub enum CellVisibility {
 }
 
 impl Map {
+    /// Map with the stock terrain and resources, and the station in the
+    /// centre.
+    #[cfg(test)]
     pub fn new(config: MapConfig) -> Self {
+        let base = config.centre();
+        Self::generate(
+            config,
+            &[base],
+            &TerrainParams::default(),
+            &ResourceParams::default(),
+        )
+    }
+
+    /// Builds the map `config` describes, shaped by `terrain` and stocked as
+    /// `resources` asks, with room cleared for a station at each of `bases`.
+    /// A station that would be walled in is moved to the nearest cell with
+    /// enough of the map in reach; `bases` on the map holds where each one
+    /// ended up.
+    pub fn generate(
+        config: MapConfig,
+        bases: &[(usize, usize)],
+        terrain: &TerrainParams,
+        resources: &ResourceParams,
+    ) -> Self {
         let mut map = Map {
             config: config.clone(),
             cells: vec![vec![CellType::Empty; config.width]; config.height],
+            amounts: vec![vec![0; config.width]; config.height],
+            site_kinds: vec![vec![None; config.width]; config.height],
             visibility: vec![vec![CellVisibility::Hidden; config.width]; config.height],
+            bases: Vec::new(),
         };
 
-        map.generate_terrain();
-        map.clear_base_area();
-        map.place_resources();
-
-        let center_x = config.width / 2;
-        let center_y = config.height / 2;
-        map.update_visibility(center_x, center_y, 3);
+        // Every random decision below draws from this stream so that a seed
+        // reproduces the whole map, not only the Perlin terrain.
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed as u64);
+
+        map.generate_terrain(terrain, &mut rng);
+        for &requested in bases {
+            let base = map.settle_base(requested);
+            if base != requested {
+                warn!(
+                    "Station at {:?} is walled in or crowded, moved to {:?}",
+                    requested, base
+                );
+            }
+            map.clear_base_area(base);
+            map.bases.push(base);
+        }
+        let bases = map.bases.clone();
+        let richness = map.richness(terrain);
+        map.place_resources(resources, &bases, &richness, &mut rng);
+        map.fill_deposits(&richness);
+        map.assign_site_kinds(&mut rng);
+
+        for &(x, y) in &bases {
+            map.update_visibility(x, y, 3);
+        }
 
         map
     }
 
-    fn generate_terrain(&mut self) {
+    fn generate_terrain(&mut self, terrain: &TerrainParams, rng: &mut impl Rng) {
         let perlin = Perlin::new(self.config.seed);
-        let scale = 0.15;
+        let scale = terrain.scale;
 
         for y in 0..self.config.height {
             for x in 0..self.config.width {
                 let val = perlin.get([x as f64 * scale, y as f64 * scale]);
-                if val > 0.2 {
+                if val > terrain.obstacle_threshold {
                     self.cells[y][x] = CellType::Obstacle;
                 }
             }
         }
 
-        let iterations = 4;
-        for _ in 0..iterations {
+        for _ in 0..terrain.smoothing_passes {
             let mut new_cells = self.cells.clone();
 
-            for y in 0..self.config.height {
-                for x in 0..self.config.width {
+            for (y, row) in new_cells.iter_mut().enumerate() {
+                for (x, cell) in row.iter_mut().enumerate() {
                     let neighbors = self.count_obstacle_neighbors(x, y);
 
-                    new_cells[y][x] = if self.cells[y][x] == CellType::Obstacle {
+                    *cell = if self.cells[y][x] == CellType::Obstacle {
                         if neighbors >= 4 {
                             CellType::Obstacle
                         } else {
@@ -90,7 +267,7 @@ impl Map {
             self.cells = new_cells;
         }
 
-        self.ensure_traversable();
+        self.ensure_traversable(rng);
     }
 
     fn count_obstacle_neighbors(&self, x: usize, y: usize) -> usize {
@@ -120,8 +297,7 @@ impl Map {
         count
     }
 
-    fn ensure_traversable(&mut self) {
-        let mut rng = rand::thread_rng();
+    fn ensure_traversable(&mut self, rng: &mut impl Rng) {
         let paths = 3;
 
         for _ in 0..paths {
@@ -169,14 +345,102 @@ impl Map {
         }
     }
 
-    fn clear_base_area(&mut self) {
-        let center_x = self.config.width / 2;
-        let center_y = self.config.height / 2;
+    /// Cell for a station asked for at `requested`: that cell if, with the
+    /// ground around it cleared, it reaches enough of the map on foot and is
+    /// away from the stations settled so far. Otherwise the nearest walkable
+    /// cell of a large enough open area, or of the largest one if none is,
+    /// away from those stations.
+    fn settle_base(&self, requested: (usize, usize)) -> (usize, usize) {
+        let (width, height) = (self.config.width, self.config.height);
+        let wanted = (MIN_STATION_REACH * (width * height) as f32) as usize;
+        let clear_of_stations = |x: usize, y: usize| {
+            self.bases
+                .iter()
+                .all(|&(bx, by)| x.abs_diff(bx) > 2 || y.abs_diff(by) > 2)
+        };
+        if clear_of_stations(requested.0, requested.1) && self.reach_from(requested) >= wanted {
+            return requested;
+        }
+
+        let (areas, sizes) = self.open_areas();
+        let needed = wanted.min(sizes.iter().copied().max().unwrap_or(0));
+        (1..height - 1)
+            .flat_map(|y| (1..width - 1).map(move |x| (x, y)))
+            .filter(|&(x, y)| areas[y][x].is_some_and(|area| sizes[area] >= needed))
+            .filter(|&(x, y)| clear_of_stations(x, y))
+            .min_by_key(|&(x, y)| x.abs_diff(requested.0) + y.abs_diff(requested.1))
+            .unwrap_or(requested)
+    }
+
+    /// Cells reachable on foot from `base`, counting the ground cleared
+    /// around a station standing there.
+    fn reach_from(&self, (base_x, base_y): (usize, usize)) -> usize {
+        let open = |x: usize, y: usize| {
+            self.is_walkable(x, y) || (x.abs_diff(base_x) <= 1 && y.abs_diff(base_y) <= 1)
+        };
+        let mut seen = vec![vec![false; self.config.width]; self.config.height];
+        seen[base_y][base_x] = true;
+        let mut queue = VecDeque::from([(base_x, base_y)]);
+        let mut reached = 0;
+        while let Some((x, y)) = queue.pop_front() {
+            reached += 1;
+            for (nx, ny) in self.neighbours(x, y) {
+                if open(nx, ny) && !seen[ny][nx] {
+                    seen[ny][nx] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        reached
+    }
+
+    /// Splits the walkable cells into areas connected on foot. Returns the
+    /// area of every cell, `None` for obstacles, and the size of each area.
+    fn open_areas(&self) -> (Vec<Vec<Option<usize>>>, Vec<usize>) {
+        let mut areas = vec![vec![None; self.config.width]; self.config.height];
+        let mut sizes = Vec::new();
+        for y in 0..self.config.height {
+            for x in 0..self.config.width {
+                if areas[y][x].is_some() || !self.is_walkable(x, y) {
+                    continue;
+                }
+
+                let area = sizes.len();
+                areas[y][x] = Some(area);
+                let mut queue = VecDeque::from([(x, y)]);
+                let mut size = 0;
+                while let Some((cx, cy)) = queue.pop_front() {
+                    size += 1;
+                    for (nx, ny) in self.neighbours(cx, cy) {
+                        if self.is_walkable(nx, ny) && areas[ny][nx].is_none() {
+                            areas[ny][nx] = Some(area);
+                            queue.push_back((nx, ny));
+                        }
+                    }
+                }
+                sizes.push(size);
+            }
+        }
+        (areas, sizes)
+    }
+
+    /// Cells a robot at (`x`, `y`) can step to, obstacles included.
+    fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.config.width, self.config.height);
+        [(0, 1), (1, 0), (0, -1), (-1, 0)]
+            .into_iter()
+            .map(move |(dx, dy)| (x as isize + dx, y as isize + dy))
+            .filter(move |&(nx, ny)| {
+                nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height
+            })
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+    }
 
+    fn clear_base_area(&mut self, (base_x, base_y): (usize, usize)) {
         for dy in -1..=1 {
             for dx in -1..=1 {
-                let x = (center_x as isize + dx) as usize;
-                let y = (center_y as isize + dy) as usize;
+                let x = (base_x as isize + dx) as usize;
+                let y = (base_y as isize + dy) as usize;
 
                 if x < self.config.width && y < self.config.height {
                     self.cells[y][x] = CellType::Empty;
@@ -185,60 +449,136 @@ impl Map {
         }
     }
 
-    pub fn place_resources(&mut self) {
-        let mut rng = rand::thread_rng();
-
-        let nb_energy = 20;
-        let nb_minerals = 20;
-        let nb_sites = 5;
+    /// Scatters deposits and sites over empty cells, keeping clear of the
+    /// stations at `bases`. Energy and mineral deposits settle more readily
+    /// on rich ground; sites take any cell.
+    pub fn place_resources(
+        &mut self,
+        resources: &ResourceParams,
+        bases: &[(usize, usize)],
+        richness: &[Vec<f64>],
+        rng: &mut impl Rng,
+    ) {
+        let cells = self.config.width * self.config.height;
+        let kinds = [
+            (CellType::Energy, resources.energy.resolve(cells), true),
+            (CellType::Mineral, resources.minerals.resolve(cells), true),
+            (
+                CellType::ScientificSite,
+                resources.science_sites.resolve(cells),
+                false,
+            ),
+        ];
 
         let is_valid_position = |x: usize, y: usize, map: &Map| -> bool {
-            let center_x = map.config.width / 2;
-            let center_y = map.config.height / 2;
-            let dx = x.abs_diff(center_x);
-            let dy = y.abs_diff(center_y);
-
-            if dx <= 1 && dy <= 1 {
+            if bases
+                .iter()
+                .any(|&(base_x, base_y)| x.abs_diff(base_x) <= 1 && y.abs_diff(base_y) <= 1)
+            {
                 return false;
             }
 
             map.cells[y][x] == CellType::Empty
         };
 
-        for _ in 0..nb_energy {
-            for _ in 0..10 {
-                let x = rng.gen_range(0..self.config.width);
-                let y = rng.gen_range(0..self.config.height);
-                if is_valid_position(x, y, self) {
-                    self.cells[y][x] = CellType::Energy;
-                    break;
+        for (kind, count, on_rich_ground) in kinds {
+            let mut missing = 0;
+            for _ in 0..count {
+                // Richest free cell drawn, taken should every draw be turned
+                // down so that poor maps still get the deposits asked for.
+                let mut best: Option<(usize, usize)> = None;
+                let mut placed = false;
+                for _ in 0..PLACEMENT_ATTEMPTS {
+                    let x = rng.gen_range(0..self.config.width);
+                    let y = rng.gen_range(0..self.config.height);
+                    if !is_valid_position(x, y, self) {
+                        continue;
+                    }
+                    // Rich cells keep the draw more often, poor ones rarely.
+                    if !on_rich_ground || rng.gen_bool(richness[y][x]) {
+                        self.cells[y][x] = kind;
+                        placed = true;
+                        break;
+                    }
+                    if best.is_none_or(|(bx, by)| richness[y][x] > richness[by][bx]) {
+                        best = Some((x, y));
+                    }
+                }
+                if placed {
+                    continue;
+                }
+                match best {
+                    Some((x, y)) => self.cells[y][x] = kind,
+                    None => missing += 1,
                 }
             }
+            if missing > 0 {
+                warn!(
+                    "No free cell found for {} of {} {:?} deposits",
+                    missing, count, kind
+                );
+            }
         }
+    }
 
-        for _ in 0..nb_minerals {
-            for _ in 0..10 {
-                let x = rng.gen_range(0..self.config.width);
-                let y = rng.gen_range(0..self.config.height);
-                if is_valid_position(x, y, self) {
-                    self.cells[y][x] = CellType::Mineral;
-                    break;
-                }
+    /// Richness of every cell in [0, 1], from a second noise field so that
+    /// rich ground comes in patches rather than at random.
+    fn richness(&self, terrain: &TerrainParams) -> Vec<Vec<f64>> {
+        let perlin = Perlin::new(self.config.seed.wrapping_add(1));
+        let scale = terrain.richness_scale;
+
+        (0..self.config.height)
+            .map(|y| {
+                (0..self.config.width)
+                    .map(|x| {
+                        let val = perlin.get([x as f64 * scale, y as f64 * scale]);
+                        ((val + 1.0) / 2.0).clamp(0.0, 1.0)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Sizes every energy and mineral deposit by the richness of its cell.
+    fn fill_deposits(&mut self, richness: &[Vec<f64>]) {
+        let spread = (MAX_DEPOSIT - MIN_DEPOSIT) as f64;
+        let cells = self.cells.iter().flatten();
+        let amounts = self.amounts.iter_mut().flatten();
+        for ((cell, amount), richness) in cells.zip(amounts).zip(richness.iter().flatten()) {
+            if matches!(cell, CellType::Energy | CellType::Mineral) {
+                *amount = MIN_DEPOSIT + (richness * spread).round() as u32;
             }
         }
+    }
 
-        for _ in 0..nb_sites {
-            for _ in 0..10 {
-                let x = rng.gen_range(0..self.config.width);
-                let y = rng.gen_range(0..self.config.height);
-                if is_valid_position(x, y, self) {
-                    self.cells[y][x] = CellType::ScientificSite;
-                    break;
+    fn assign_site_kinds(&mut self, rng: &mut impl Rng) {
+        for y in 0..self.config.height {
+            for x in 0..self.config.width {
+                if self.cells[y][x] == CellType::ScientificSite {
+                    let kind = SiteKind::ALL[rng.gen_range(0..SiteKind::ALL.len())];
+                    self.site_kinds[y][x] = Some(kind);
+                    self.amounts[y][x] = kind.yield_budget();
                 }
             }
         }
     }
 
+    /// Removes one unit from the deposit or site at (`x`, `y`); the cell
+    /// becomes empty once the last unit is taken. Returns false if nothing
+    /// was left.
+    pub fn extract_unit(&mut self, x: usize, y: usize) -> bool {
+        if self.amounts[y][x] == 0 {
+            return false;
+        }
+
+        self.amounts[y][x] -= 1;
+        if self.amounts[y][x] == 0 {
+            self.cells[y][x] = CellType::Empty;
+            self.site_kinds[y][x] = None;
+        }
+        true
+    }
+
     pub fn is_walkable(&self, x: usize, y: usize) -> bool {
         if x >= self.config.width || y >= self.config.height {
             return false;
@@ -278,3 +618,210 @@ impl Map {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: [u32; 4] = [0, 1, 42, 1337];
+
+    fn generate(seed: u32) -> Map {
+        Map::new(MapConfig {
+            width: 50,
+            height: 30,
+            seed,
+        })
+    }
+
+    /// FNV-1a over the cell grid. Unlike `DefaultHasher`, its output is stable
+    /// across Rust releases, so recorded values stay valid.
+    fn hash_cells(map: &Map) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for cell in map.cells.iter().flatten() {
+            hash ^= *cell as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+
+    #[test]
+    fn same_seed_generates_same_cells() {
+        for seed in SEEDS {
+            let (a, b) = (generate(seed), generate(seed));
+            assert_eq!(a.cells, b.cells, "seed {seed}");
+            assert_eq!(a.amounts, b.amounts, "seed {seed}");
+            assert_eq!(a.site_kinds, b.site_kinds, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn deposits_and_sites_hold_finite_amounts() {
+        let mut map = generate(42);
+
+        for y in 0..map.config.height {
+            for x in 0..map.config.width {
+                let amount = map.amounts[y][x];
+                match map.cells[y][x] {
+                    CellType::Energy | CellType::Mineral => {
+                        assert!((MIN_DEPOSIT..=MAX_DEPOSIT).contains(&amount))
+                    }
+                    CellType::ScientificSite => {
+                        let kind = map.site_kinds[y][x].unwrap();
+                        assert_eq!(amount, kind.yield_budget());
+                    }
+                    _ => assert_eq!(amount, 0),
+                }
+            }
+        }
+
+        let (x, y) = (0..map.config.height)
+            .flat_map(|y| (0..map.config.width).map(move |x| (x, y)))
+            .find(|&(x, y)| map.cells[y][x] == CellType::Mineral)
+            .unwrap();
+        let amount = map.amounts[y][x];
+        for _ in 0..amount {
+            assert_eq!(map.cells[y][x], CellType::Mineral);
+            assert!(map.extract_unit(x, y));
+        }
+        assert_eq!(map.cells[y][x], CellType::Empty);
+        assert!(!map.extract_unit(x, y));
+    }
+
+    #[test]
+    fn rich_ground_holds_more_deposits() {
+        let config = MapConfig {
+            width: 120,
+            height: 80,
+            seed: 7,
+        };
+        let resources = ResourceParams {
+            energy: Abundance::count(300),
+            minerals: Abundance::count(300),
+            science_sites: Abundance::count(0),
+        };
+        let terrain = TerrainParams::default();
+        let map = Map::generate(config, &[(60, 40)], &terrain, &resources);
+        let richness = map.richness(&terrain);
+
+        // Deposits per open cell, on the poorer and the richer half.
+        let mut open = [0; 2];
+        let mut deposits = [0; 2];
+        for (cell, richness) in map.cells.iter().flatten().zip(richness.iter().flatten()) {
+            let half = usize::from(*richness >= 0.5);
+            match cell {
+                CellType::Obstacle => {}
+                CellType::Energy | CellType::Mineral => {
+                    open[half] += 1;
+                    deposits[half] += 1;
+                }
+                _ => open[half] += 1,
+            }
+        }
+        let density = |half: usize| deposits[half] as f64 / open[half] as f64;
+        assert!(
+            density(1) > 1.5 * density(0),
+            "rich {deposits:?} of {open:?}"
+        );
+    }
+
+    #[test]
+    fn poor_ground_still_gets_every_deposit() {
+        let config = MapConfig {
+            width: 50,
+            height: 30,
+            seed: 3,
+        };
+        let empty = ResourceParams {
+            energy: Abundance::count(0),
+            minerals: Abundance::count(0),
+            science_sites: Abundance::count(0),
+        };
+        let mut map = Map::generate(config, &[(25, 15)], &TerrainParams::default(), &empty);
+        let bases = map.bases.clone();
+
+        // Ground so poor that every draw is turned down.
+        let richness = vec![vec![0.0; 50]; 30];
+        let resources = ResourceParams {
+            energy: Abundance::count(12),
+            minerals: Abundance::count(8),
+            science_sites: Abundance::count(0),
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        map.place_resources(&resources, &bases, &richness, &mut rng);
+
+        let count = |kind| map.cells.iter().flatten().filter(|&&c| c == kind).count();
+        assert_eq!(count(CellType::Energy), 12);
+        assert_eq!(count(CellType::Mineral), 8);
+    }
+
+    #[test]
+    fn different_seeds_generate_different_cells() {
+        assert_ne!(hash_cells(&generate(1)), hash_cells(&generate(2)));
+    }
+
+    #[test]
+    fn walled_in_stations_move_to_open_ground() {
+        let mut map = generate(42);
+        let centre = map.config.centre();
+        assert_eq!(map.bases, vec![centre]);
+        assert_ne!(map.settle_base(centre), centre);
+        map.bases.clear();
+        assert_eq!(map.settle_base(centre), centre);
+
+        // Rock all round the 3x3 the station would clear.
+        let (x, y) = (10, 10);
+        for dy in -2..=2_isize {
+            for dx in -2..=2_isize {
+                if dx.abs() == 2 || dy.abs() == 2 {
+                    let (cx, cy) = ((x as isize + dx) as usize, (y as isize + dy) as usize);
+                    map.cells[cy][cx] = CellType::Obstacle;
+                }
+            }
+        }
+        assert_eq!(map.reach_from((x, y)), 9);
+
+        let settled = map.settle_base((x, y));
+        assert_ne!(settled, (x, y));
+        assert!(map.reach_from(settled) >= 150, "{:?}", settled);
+    }
+
+    #[test]
+    fn stations_asked_for_on_top_of_each_other_are_kept_apart() {
+        let config = MapConfig {
+            width: 50,
+            height: 30,
+            seed: 42,
+        };
+        let requested = [(25, 15), (25, 15), (26, 16)];
+        let map = Map::generate(
+            config,
+            &requested,
+            &TerrainParams::default(),
+            &ResourceParams::default(),
+        );
+
+        assert_eq!(map.bases.len(), requested.len());
+        for (i, &(ax, ay)) in map.bases.iter().enumerate() {
+            for &(bx, by) in &map.bases[i + 1..] {
+                assert!(
+                    ax.abs_diff(bx) > 2 || ay.abs_diff(by) > 2,
+                    "{:?}",
+                    map.bases
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cell_hashes_match_recorded_values() {
+        let recorded: [(u32, u64); 4] = [
+            (0, 0x2f88_7025_7aa4_35a7),
+            (1, 0x1938_1804_b7a8_5af5),
+            (42, 0x1175_99b7_17bd_bdc1),
+            (1337, 0xd18d_a547_45da_e6da),
+        ];
+        for (seed, expected) in recorded {
+            assert_eq!(hash_cells(&generate(seed)), expected, "seed {seed}");
+        }
+    }
+}
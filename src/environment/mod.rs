@@ -1,3 +1,6 @@
+pub mod frontier;
+pub mod knowledge;
 pub mod map;
 
+pub use self::knowledge::{KnowledgeCommit, KnowledgeMap};
 pub use self::map::{Map, MapConfig}; 
@@ -1,6 +1,6 @@
-use env_logger;
 use log::info;
 
+mod cli;
 mod environment;
 mod pathfinding;
 mod robot;
@@ -8,35 +8,123 @@ mod simulation;
 mod station;
 mod ui;
 
+use cli::Cli;
 use crossterm::event::{self, Event, KeyCode};
-use std::{io, time::Duration};
+use robot::behavior_tree::TreeLibrary;
+use simulation::batch::{run_batch, summary_table};
+use simulation::recorder::{MetricsFormat, MetricsRecorder, MetricsSnapshot};
+use simulation::scenario::Scenario;
+use simulation::Simulation;
+use std::{io, thread, time::Duration};
 
 fn main() -> Result<(), io::Error> {
     env_logger::init();
+    let cli = Cli::parse_and_check();
     info!("Starting EREEA simulation...");
 
-    let mut ui = ui::Ui::new()?;
-    let mut sim = simulation::Simulation::new();
+    let trees = match &cli.behavior_trees {
+        Some(path) => {
+            let trees = TreeLibrary::load(path)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+            info!("Loaded behaviour trees from {}", path.display());
+            Some(trees)
+        }
+        None => None,
+    };
+
+    let scenario = cli
+        .scenario()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+    info!("Running scenario \"{}\"", scenario.name);
+    info!(
+        "Stations build robots with the {} plan",
+        scenario.station.build_plan
+    );
+    info!(
+        "Allocating tasks with the {} strategy",
+        scenario.station.allocation
+    );
+
+    let scenario_ref = &scenario;
+    let build = move |seed| build_simulation(scenario_ref, seed, trees.as_ref());
 
-    let max_steps = 1000;
+    let mut recorder = match &cli.metrics {
+        Some(path) => {
+            let format = MetricsFormat::from_path(path).expect("checked by Cli");
+            info!(
+                "Recording {} metrics every {} steps to {}",
+                format,
+                cli.metrics_interval,
+                path.display()
+            );
+            Some(MetricsRecorder::create(path, format, cli.metrics_interval)?)
+        }
+        None => None,
+    };
+
+    let max_steps = scenario.steps;
+
+    if cli.is_headless() {
+        let snapshots = match recorder.as_mut() {
+            Some(recorder) => {
+                let mut sim = build(scenario.seed);
+                while sim.stats.simulation_step < max_steps && !sim.is_mission_complete() {
+                    sim.update();
+                    recorder.record(&sim)?;
+                }
+                vec![MetricsSnapshot::of(&sim)]
+            }
+            None => {
+                let threads = cli
+                    .threads
+                    .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+                info!(
+                    "Running {} seeds headless on {} threads for {} steps each",
+                    cli.runs, threads, max_steps
+                );
+                run_batch(&cli.seeds(scenario.seed), max_steps, threads, build)
+            }
+        };
+        print!("{}", summary_table(&snapshots));
+
+        if let Some(path) = &cli.summary {
+            let format = MetricsFormat::from_path(path).expect("checked by Cli");
+            let mut summary = MetricsRecorder::create(path, format, 1)?;
+            for snapshot in &snapshots {
+                summary.write(snapshot)?;
+            }
+            summary.flush()?;
+            info!("Wrote the final stats of every run to {}", path.display());
+        }
+        return Ok(());
+    }
+
+    let mut sim = build(scenario.seed);
+
+    let mut ui = ui::Ui::new()?;
 
     loop {
         sim.update();
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record(&sim)?;
+        }
 
         ui.draw(&sim)?;
 
-        if crossterm::event::poll(Duration::from_millis(100))? {
+        if crossterm::event::poll(Duration::from_millis(cli.tick_ms))? {
             if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => {
-                        info!("User requested exit. Terminating simulation.");
-                        break;
-                    }
-                    _ => {}
+                if key.code == KeyCode::Char('q') {
+                    info!("User requested exit. Terminating simulation.");
+                    break;
                 }
             }
         }
 
+        if sim.is_mission_complete() {
+            info!("Mission complete. Terminating.");
+            break;
+        }
+
         if sim.stats.simulation_step >= max_steps {
             info!(
                 "Reached maximum simulation steps ({}). Terminating.",
@@ -45,7 +133,7 @@ fn main() -> Result<(), io::Error> {
             break;
         }
 
-        if sim.stats.simulation_step % 100 == 0 {
+        if sim.stats.simulation_step.is_multiple_of(100) {
             info!("Simulation step: {}", sim.stats.simulation_step);
             info!("Robots: {}", sim.robots.len());
             info!(
@@ -69,3 +157,18 @@ fn main() -> Result<(), io::Error> {
 
     Ok(())
 }
+
+/// The run of `scenario` on `seed`, with `trees` in place of the bundled
+/// behaviour trees if given.
+fn build_simulation(scenario: &Scenario, seed: u32, trees: Option<&TreeLibrary>) -> Simulation {
+    match trees {
+        Some(trees) => {
+            Simulation::with_behavior_trees(&scenario.simulation_config(seed), trees.clone())
+        }
+        None => Simulation::from_scenario(&Scenario {
+            seed,
+            ..scenario.clone()
+        })
+        .expect("checked by Cli"),
+    }
+}
@@ -1,7 +1,13 @@
 use std::cmp::Ordering;
+use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap};
 
-use crate::environment::Map;
+use crate::environment::map::CellType;
+use crate::environment::KnowledgeMap;
+
+/// Cost of stepping into a cell nobody has seen yet. It may well be an
+/// obstacle, so known ground is preferred when the detour is short.
+const UNKNOWN_STEP_COST: u32 = 3;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 struct Node {
@@ -22,8 +28,10 @@ impl PartialOrd for Node {
     }
 }
 
+/// Cheapest path from `start` to `goal`, both included, over what
+/// `knowledge` shows. Costs are those of `distances_from`.
 pub fn find_path(
-    map: &Map,
+    knowledge: &KnowledgeMap,
     start: (usize, usize),
     goal: (usize, usize),
 ) -> Option<Vec<(usize, usize)>> {
@@ -45,8 +53,8 @@ pub fn find_path(
             return Some(reconstruct_path(came_from, current.position));
         }
 
-        for neighbor in get_neighbors(map, current.position) {
-            let tentative_g_score = g_scores[&current.position] + 1;
+        for (neighbor, step) in get_neighbors(knowledge, current.position) {
+            let tentative_g_score = g_scores[&current.position] + step as i32;
 
             if !g_scores.contains_key(&neighbor) || tentative_g_score < g_scores[&neighbor] {
                 came_from.insert(neighbor, current.position);
@@ -66,11 +74,54 @@ pub fn find_path(
     None
 }
 
+/// Cost of the cheapest path from `start` to every cell of `knowledge`,
+/// `None` where no path exists. Known obstacles block the way and unknown
+/// cells cost `UNKNOWN_STEP_COST`, so a robot plans only from what it has
+/// seen. Cheaper than one search per goal when many goals are weighed at
+/// once.
+pub fn distances_from(knowledge: &KnowledgeMap, start: (usize, usize)) -> Vec<Vec<Option<u32>>> {
+    let mut distances = vec![vec![None; knowledge.width]; knowledge.height];
+    distances[start.1][start.0] = Some(0);
+    let mut queue = BinaryHeap::from([Reverse((0, start))]);
+
+    while let Some(Reverse((cost, (x, y)))) = queue.pop() {
+        if distances[y][x].is_some_and(|known| known < cost) {
+            continue;
+        }
+
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            if nx >= knowledge.width || ny >= knowledge.height {
+                continue;
+            }
+            let step = match knowledge.cell(nx, ny) {
+                Some(CellType::Obstacle) => continue,
+                Some(_) => 1,
+                None => UNKNOWN_STEP_COST,
+            };
+            let next = cost + step;
+            if distances[ny][nx].is_none_or(|known| next < known) {
+                distances[ny][nx] = Some(next);
+                queue.push(Reverse((next, (nx, ny))));
+            }
+        }
+    }
+
+    distances
+}
+
 fn manhattan_distance(a: (usize, usize), b: (usize, usize)) -> i32 {
     (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as i32
 }
 
-fn get_neighbors(map: &Map, pos: (usize, usize)) -> Vec<(usize, usize)> {
+/// Cells next to `pos` not known to be obstacles, with the cost of stepping
+/// into each.
+fn get_neighbors(knowledge: &KnowledgeMap, pos: (usize, usize)) -> Vec<((usize, usize), u32)> {
     let mut neighbors = Vec::new();
     let directions = [(0, 1), (1, 0), (0, -1), (-1, 0)];
 
@@ -79,13 +130,15 @@ fn get_neighbors(map: &Map, pos: (usize, usize)) -> Vec<(usize, usize)> {
         let new_y = pos.1 as isize + dy;
 
         if new_x >= 0
-            && new_x < map.config.width as isize
+            && new_x < knowledge.width as isize
             && new_y >= 0
-            && new_y < map.config.height as isize
+            && new_y < knowledge.height as isize
         {
             let new_pos = (new_x as usize, new_y as usize);
-            if map.is_walkable(new_pos.0, new_pos.1) {
-                neighbors.push(new_pos);
+            match knowledge.cell(new_pos.0, new_pos.1) {
+                Some(CellType::Obstacle) => {}
+                Some(_) => neighbors.push((new_pos, 1)),
+                None => neighbors.push((new_pos, UNKNOWN_STEP_COST)),
             }
         }
     }
@@ -105,3 +158,49 @@ fn reconstruct_path(
     path.reverse();
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_ground_costs_more_and_known_obstacles_block() {
+        let mut knowledge = KnowledgeMap::new(5, 3);
+        for x in 0..5 {
+            knowledge.observe(x, 0, CellType::Empty, 0);
+        }
+        knowledge.observe(1, 1, CellType::Obstacle, 0);
+
+        let distances = distances_from(&knowledge, (0, 0));
+
+        assert_eq!(distances[0][4], Some(4));
+        assert_eq!(distances[1][0], Some(UNKNOWN_STEP_COST));
+        assert_eq!(distances[1][2], Some(2 + UNKNOWN_STEP_COST));
+        assert_eq!(distances[1][1], None);
+    }
+
+    #[test]
+    fn paths_keep_to_known_ground_unless_the_detour_is_long() {
+        // Known clear ground along the top and bottom rows, unknown between.
+        let mut knowledge = KnowledgeMap::new(5, 5);
+        for x in 0..5 {
+            knowledge.observe(x, 0, CellType::Empty, 0);
+            knowledge.observe(x, 4, CellType::Empty, 0);
+        }
+        assert_eq!(
+            find_path(&knowledge, (0, 0), (4, 0)).unwrap(),
+            vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]
+        );
+
+        // With the top row cut, skirting the rock through unknown ground is
+        // cheaper than the known bottom row.
+        knowledge.observe(2, 0, CellType::Obstacle, 0);
+        let path = find_path(&knowledge, (0, 0), (4, 0)).unwrap();
+        assert!(path.contains(&(2, 1)), "{path:?}");
+
+        for x in 0..5 {
+            knowledge.observe(x, 1, CellType::Obstacle, 0);
+        }
+        assert_eq!(find_path(&knowledge, (0, 0), (4, 0)), None);
+    }
+}
@@ -0,0 +1,462 @@
+use super::{Capability, RobotModule};
+use crate::environment::frontier::{best_frontier, frontier_clusters, FrontierCluster};
+use crate::environment::map::{CellType, SiteKind};
+use crate::environment::KnowledgeMap;
+use crate::pathfinding;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// What a behaviour gets to see of its robot and surroundings when deciding.
+pub struct RobotView<'a> {
+    pub x: usize,
+    pub y: usize,
+    pub modules: &'a [RobotModule],
+    /// Where the robot unloads and recharges.
+    pub base: (usize, usize),
+    pub cargo_load: u32,
+    pub cargo_full: bool,
+    /// The battery only just covers the trip home.
+    pub low_battery: bool,
+    /// Standing on a deposit or site the robot can work.
+    pub can_work_here: bool,
+    /// Cell of the task the robot claimed on the station's board.
+    pub task: Option<(usize, usize)>,
+    /// Cells other robots have claimed and should be left alone.
+    pub reserved: Vec<(usize, usize)>,
+    /// Positions of every other robot.
+    pub peers: Vec<(usize, usize)>,
+}
+
+impl RobotView<'_> {
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        self.modules
+            .iter()
+            .any(|m| m.capabilities().contains(&capability))
+    }
+
+    pub fn can_sample(&self, kind: SiteKind) -> bool {
+        self.has_capability(Capability::Sample(kind))
+    }
+
+    pub fn is_at_base(&self) -> bool {
+        self.x.abs_diff(self.base.0) <= 1 && self.y.abs_diff(self.base.1) <= 1
+    }
+}
+
+/// What a robot does this tick. Gathering is not an action: a robot always
+/// works the cell it ends the tick on if it can.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Stay,
+    MoveTo { x: usize, y: usize },
+    ReturnToBase,
+    Wander,
+}
+
+/// Decision making for a robot, called once per tick.
+pub trait Behavior: Debug + Send {
+    fn decide(&self, view: &RobotView, knowledge: &KnowledgeMap) -> Action;
+}
+
+/// Part a robot plays in the swarm, from its modules' capabilities. Each
+/// role has a built-in behaviour and a behaviour tree of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Hauler,
+    Relay,
+    Explorer,
+    Miner,
+    Collector,
+}
+
+impl Role {
+    /// Every role, in the order they are tried against a robot's modules.
+    pub const ALL: [Role; 5] = [
+        Role::Hauler,
+        Role::Relay,
+        Role::Explorer,
+        Role::Miner,
+        Role::Collector,
+    ];
+
+    /// Capability that earns a robot the role. Collector takes whoever is
+    /// left.
+    fn capability(&self) -> Option<Capability> {
+        match self {
+            Role::Hauler => Some(Capability::Haul),
+            Role::Relay => Some(Capability::Relay),
+            Role::Explorer => Some(Capability::Explore),
+            Role::Miner => Some(Capability::Mine),
+            Role::Collector => None,
+        }
+    }
+
+    /// The first role whose capability one of `modules` brings.
+    pub fn of(modules: &[RobotModule]) -> Role {
+        Role::ALL
+            .into_iter()
+            .find(|role| {
+                role.capability().is_none_or(|capability| {
+                    modules
+                        .iter()
+                        .any(|m| m.capabilities().contains(&capability))
+                })
+            })
+            .unwrap_or(Role::Collector)
+    }
+
+    /// Name of the role's tree in a behaviour tree library.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Hauler => "hauler",
+            Role::Relay => "relay",
+            Role::Explorer => "explorer",
+            Role::Miner => "miner",
+            Role::Collector => "collector",
+        }
+    }
+
+    pub fn default_behavior(&self) -> Box<dyn Behavior> {
+        match self {
+            Role::Hauler => Box::new(Hauler),
+            Role::Relay => Box::new(Relay),
+            Role::Explorer => Box::new(Explorer),
+            Role::Miner => Box::new(Harvester::MINER),
+            Role::Collector => Box::new(Harvester::COLLECTOR),
+        }
+    }
+
+    /// What the role's built-in behaviour does this tick.
+    pub fn decide(&self, view: &RobotView, knowledge: &KnowledgeMap) -> Action {
+        match self {
+            Role::Hauler => Hauler.decide(view, knowledge),
+            Role::Relay => Relay.decide(view, knowledge),
+            Role::Explorer => Explorer.decide(view, knowledge),
+            Role::Miner => Harvester::MINER.decide(view, knowledge),
+            Role::Collector => Harvester::COLLECTOR.decide(view, knowledge),
+        }
+    }
+}
+
+/// Behaviour a robot gets by default, from its modules' capabilities.
+pub fn default_behavior(modules: &[RobotModule]) -> Box<dyn Behavior> {
+    Role::of(modules).default_behavior()
+}
+
+/// Samples what it can, otherwise heads for the most promising frontier.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Explorer;
+
+/// Drives miners and energy collectors: fills the hold from the closest
+/// deposit or site its modules can work, energy first for collectors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Harvester {
+    pub energy_first: bool,
+}
+
+impl Harvester {
+    pub const MINER: Harvester = Harvester {
+        energy_first: false,
+    };
+    pub const COLLECTOR: Harvester = Harvester { energy_first: true };
+}
+
+/// Stays in the middle of the swarm, where it reaches the most robots.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Relay;
+
+/// Shuttles between stations. Its base is whichever station it is due at
+/// next, so it only ever heads there and waits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hauler;
+
+impl Behavior for Explorer {
+    fn decide(&self, view: &RobotView, knowledge: &KnowledgeMap) -> Action {
+        errand(view).unwrap_or_else(|| {
+            view.task
+                .or_else(|| find_sampling_site(view, knowledge))
+                .or_else(|| best_frontier_target(view, knowledge))
+                .map(|(x, y)| Action::MoveTo { x, y })
+                .unwrap_or_else(|| idle(view))
+        })
+    }
+}
+
+impl Behavior for Harvester {
+    fn decide(&self, view: &RobotView, knowledge: &KnowledgeMap) -> Action {
+        let deposit = |kind: CellType, capability: Capability| {
+            view.has_capability(capability)
+                .then(|| find_nearest_resource(view.x, view.y, knowledge, kind, &view.reserved))
+                .flatten()
+        };
+        let minerals_or_sites = || {
+            [
+                deposit(CellType::Mineral, Capability::Mine),
+                find_sampling_site(view, knowledge),
+            ]
+            .into_iter()
+            .flatten()
+            .min_by_key(|&(x, y)| view.x.abs_diff(x).pow(2) + view.y.abs_diff(y).pow(2))
+        };
+        let energy = || deposit(CellType::Energy, Capability::HarvestEnergy);
+
+        errand(view).unwrap_or_else(|| {
+            view.task
+                .or_else(|| {
+                    if self.energy_first {
+                        energy().or_else(minerals_or_sites)
+                    } else {
+                        minerals_or_sites().or_else(energy)
+                    }
+                })
+                .map(|(x, y)| Action::MoveTo { x, y })
+                .unwrap_or_else(|| idle(view))
+        })
+    }
+}
+
+impl Behavior for Relay {
+    fn decide(&self, view: &RobotView, _knowledge: &KnowledgeMap) -> Action {
+        if view.low_battery {
+            go_home(view)
+        } else {
+            swarm_centroid(&view.peers)
+                .map(|(x, y)| Action::MoveTo { x, y })
+                .unwrap_or(Action::Wander)
+        }
+    }
+}
+
+impl Behavior for Hauler {
+    fn decide(&self, view: &RobotView, _knowledge: &KnowledgeMap) -> Action {
+        go_home(view)
+    }
+}
+
+/// Heads for the base and waits there.
+fn go_home(view: &RobotView) -> Action {
+    if view.is_at_base() {
+        Action::Stay
+    } else {
+        Action::ReturnToBase
+    }
+}
+
+/// What every robot does before looking for work: head home when the hold
+/// is full or the battery low, wait there for a recharge, and stay on a
+/// deposit until it has been worked.
+fn errand(view: &RobotView) -> Option<Action> {
+    if view.cargo_full || view.low_battery {
+        Some(go_home(view))
+    } else if view.can_work_here {
+        Some(Action::Stay)
+    } else {
+        None
+    }
+}
+
+/// Nothing to do: bring back what we have, or wander.
+fn idle(view: &RobotView) -> Action {
+    if view.cargo_load > 0 && !view.is_at_base() {
+        Action::ReturnToBase
+    } else {
+        Action::Wander
+    }
+}
+
+/// Average position of `peers`, where a relay reaches the most.
+pub(super) fn swarm_centroid(peers: &[(usize, usize)]) -> Option<(usize, usize)> {
+    if peers.is_empty() {
+        return None;
+    }
+
+    let count = peers.len();
+    let x = peers.iter().map(|&(x, _)| x).sum::<usize>() / count;
+    let y = peers.iter().map(|&(_, y)| y).sum::<usize>() / count;
+    Some((x, y))
+}
+
+/// Target of the frontier cluster worth the most per step from the robot,
+/// leaving out clusters other robots have claimed. Clusters another robot
+/// stands closer to are left to it while others remain, so explorers
+/// starting together fan out.
+pub(super) fn best_frontier_target(
+    view: &RobotView,
+    knowledge: &KnowledgeMap,
+) -> Option<(usize, usize)> {
+    let clusters = frontier_clusters(knowledge);
+    let distances = pathfinding::distances_from(knowledge, (view.x, view.y));
+    let claimed =
+        |cluster: &FrontierCluster| view.reserved.iter().any(|&(x, y)| cluster.contains(x, y));
+    let closer_peer = |cluster: &FrontierCluster| {
+        let (tx, ty) = cluster.target;
+        let mine = view.x.abs_diff(tx) + view.y.abs_diff(ty);
+        view.peers
+            .iter()
+            .any(|&(px, py)| px.abs_diff(tx) + py.abs_diff(ty) < mine)
+    };
+    best_frontier(&clusters, &distances, |cluster| {
+        claimed(cluster) || closer_peer(cluster)
+    })
+    .or_else(|| best_frontier(&clusters, &distances, claimed))
+    .map(|cluster| cluster.target)
+}
+
+/// Nearest scientific site the robot knows of and can sample. Sites whose
+/// kind went unseen are worth a look; whether the site is exhausted is only
+/// learnt by going there.
+pub(super) fn find_sampling_site(
+    view: &RobotView,
+    knowledge: &KnowledgeMap,
+) -> Option<(usize, usize)> {
+    let mut closest_dist = usize::MAX;
+    let mut closest_point = None;
+
+    for y in 0..knowledge.height {
+        for x in 0..knowledge.width {
+            if knowledge.cell(x, y) == Some(CellType::ScientificSite)
+                && !view.reserved.contains(&(x, y))
+                && knowledge
+                    .site_kind(x, y)
+                    .is_none_or(|kind| view.can_sample(kind))
+            {
+                let dist = view.x.abs_diff(x).pow(2) + view.y.abs_diff(y).pow(2);
+
+                if dist < closest_dist {
+                    closest_dist = dist;
+                    closest_point = Some((x, y));
+                }
+            }
+        }
+    }
+
+    closest_point
+}
+
+pub(super) fn find_nearest_resource(
+    robot_x: usize,
+    robot_y: usize,
+    knowledge: &KnowledgeMap,
+    resource_type: CellType,
+    reserved: &[(usize, usize)],
+) -> Option<(usize, usize)> {
+    let mut closest_dist = f32::MAX;
+    let mut closest_point = None;
+
+    for y in 0..knowledge.height {
+        for x in 0..knowledge.width {
+            if knowledge.cell(x, y) == Some(resource_type) && !reserved.contains(&(x, y)) {
+                let dist = ((x as isize - robot_x as isize).pow(2)
+                    + (y as isize - robot_y as isize).pow(2)) as f32;
+
+                if dist < closest_dist {
+                    closest_dist = dist;
+                    closest_point = Some((x, y));
+                }
+            }
+        }
+    }
+
+    closest_point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view_on(modules: &[RobotModule]) -> RobotView<'_> {
+        RobotView {
+            x: 10,
+            y: 10,
+            modules,
+            base: (25, 15),
+            cargo_load: 0,
+            cargo_full: false,
+            low_battery: false,
+            can_work_here: false,
+            task: None,
+            reserved: Vec::new(),
+            peers: vec![(20, 10), (30, 20)],
+        }
+    }
+
+    #[test]
+    fn errands_come_before_looking_for_work() {
+        let knowledge = KnowledgeMap::new(50, 30);
+        let mut view = view_on(&[RobotModule::Drill]);
+
+        view.can_work_here = true;
+        assert_eq!(Harvester::MINER.decide(&view, &knowledge), Action::Stay);
+
+        view.cargo_full = true;
+        assert_eq!(
+            Harvester::MINER.decide(&view, &knowledge),
+            Action::ReturnToBase
+        );
+
+        view.cargo_full = false;
+        view.low_battery = true;
+        (view.x, view.y) = view.base;
+        assert_eq!(Harvester::MINER.decide(&view, &knowledge), Action::Stay);
+    }
+
+    #[test]
+    fn roles_fall_back_differently_with_no_work_known() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        for y in 12..=16 {
+            for x in 13..=17 {
+                knowledge.observe(x, y, CellType::Empty, 0);
+            }
+        }
+
+        let view = view_on(&[RobotModule::Drill]);
+        assert_eq!(Harvester::MINER.decide(&view, &knowledge), Action::Wander);
+
+        let mut view = view_on(&[RobotModule::Exploration]);
+        (view.x, view.y) = (15, 14);
+        assert!(matches!(
+            Explorer.decide(&view, &knowledge),
+            Action::MoveTo { .. }
+        ));
+
+        let view = view_on(&[RobotModule::CommsRelay]);
+        assert_eq!(
+            Relay.decide(&view, &knowledge),
+            Action::MoveTo { x: 25, y: 15 }
+        );
+    }
+
+    #[test]
+    fn harvesters_try_their_own_resource_first() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        knowledge.observe(14, 10, CellType::Mineral, 0);
+        knowledge.observe(11, 12, CellType::Mineral, 0);
+        knowledge.observe(10, 11, CellType::Energy, 0);
+        knowledge.observe(30, 10, CellType::Energy, 0);
+
+        let modules = [RobotModule::Drill, RobotModule::EnergyCollector];
+        let view = view_on(&modules);
+        assert_eq!(
+            Harvester::MINER.decide(&view, &knowledge),
+            Action::MoveTo { x: 11, y: 12 }
+        );
+        assert_eq!(
+            Harvester::COLLECTOR.decide(&view, &knowledge),
+            Action::MoveTo { x: 10, y: 11 }
+        );
+    }
+
+    #[test]
+    fn sites_are_weighed_by_the_kind_seen_there() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        knowledge.observe_site(12, 10, SiteKind::Chemical, 0);
+        knowledge.observe_site(15, 10, SiteKind::Geological, 0);
+
+        let view = view_on(&[RobotModule::Drill]);
+        assert_eq!(
+            Harvester::MINER.decide(&view, &knowledge),
+            Action::MoveTo { x: 15, y: 10 }
+        );
+    }
+}
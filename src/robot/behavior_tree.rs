@@ -0,0 +1,552 @@
+use super::behavior::{
+    best_frontier_target, find_nearest_resource, find_sampling_site, swarm_centroid, Action,
+    Behavior, RobotView, Role,
+};
+use super::{Capability, RobotModule};
+use crate::environment::map::CellType;
+use crate::environment::KnowledgeMap;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Trees shipped with the simulation: each role runs its built-in behaviour.
+const DEFAULT_TREES: &str = include_str!("../../assets/behavior_trees.json");
+
+/// Result of ticking a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Success,
+    Failure,
+    /// The node has picked what the robot does this tick.
+    Running(Action),
+}
+
+/// Something a robot can head for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    Mineral,
+    Energy,
+    /// Scientific sites of a kind the robot can sample.
+    Site,
+}
+
+/// A behaviour tree node. The tree is ticked from the root every tick and
+/// the first leaf that returns `Running` decides the robot's action. Leaves
+/// without parameters are empty struct variants so that stray fields on
+/// them are refused as well.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "node", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Node {
+    /// Ticks children in order until one does not succeed.
+    Sequence {
+        children: Vec<Node>,
+    },
+    /// Ticks children in order until one does not fail.
+    Selector {
+        children: Vec<Node>,
+    },
+    /// Swaps success and failure.
+    Inverter {
+        child: Box<Node>,
+    },
+    /// Ticks `child` again as soon as it succeeds, within the same tick, and
+    /// succeeds once it has done so `times` times, then counts over. Without
+    /// `times`, it repeats until the child fails, holding the robot where it
+    /// is for the tick should the child succeed twice in one. A failing child
+    /// fails the repeat and resets the count.
+    Repeat {
+        #[serde(default)]
+        times: Option<u32>,
+        child: Box<Node>,
+        #[serde(skip)]
+        completed: Cell<u32>,
+    },
+
+    CargoFull {},
+    HasCargo {},
+    LowBattery {},
+    AtBase {},
+
+    /// Works the deposit or site under the robot, if it can.
+    Gather {},
+    /// Heads for the task the robot claimed on the station's board.
+    MoveToTask {},
+    /// Heads for the closest of `targets` the robot knows of and can work,
+    /// other than cells claimed by other robots.
+    MoveToNearest {
+        targets: Vec<Target>,
+    },
+    /// Heads for the frontier cluster worth the most per step travelled.
+    Explore {},
+    /// Heads for the middle of the other robots.
+    MoveToSwarm {},
+    /// Heads home; succeeds once there.
+    ReturnToBase {},
+    Wait {},
+    Wander {},
+    /// Hands the tick to a role's built-in behaviour.
+    Builtin {
+        role: Role,
+    },
+}
+
+impl Node {
+    pub fn tick(&self, view: &RobotView, knowledge: &KnowledgeMap) -> Status {
+        match self {
+            Node::Sequence { children } => {
+                for child in children {
+                    match child.tick(view, knowledge) {
+                        Status::Success => {}
+                        status => return status,
+                    }
+                }
+                Status::Success
+            }
+            Node::Selector { children } => {
+                for child in children {
+                    match child.tick(view, knowledge) {
+                        Status::Failure => {}
+                        status => return status,
+                    }
+                }
+                Status::Failure
+            }
+            Node::Inverter { child } => match child.tick(view, knowledge) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                running => running,
+            },
+            Node::Repeat {
+                times,
+                child,
+                completed,
+            } => {
+                let mut restarted = false;
+                loop {
+                    match child.tick(view, knowledge) {
+                        Status::Success => {
+                            completed.set(completed.get() + 1);
+                            if times.is_some_and(|times| completed.get() >= times) {
+                                completed.set(0);
+                                break Status::Success;
+                            }
+                            if restarted && times.is_none() {
+                                break Status::Running(Action::Stay);
+                            }
+                            restarted = true;
+                        }
+                        Status::Failure => {
+                            completed.set(0);
+                            break Status::Failure;
+                        }
+                        running => break running,
+                    }
+                }
+            }
+
+            Node::CargoFull {} => condition(view.cargo_full),
+            Node::HasCargo {} => condition(view.cargo_load > 0),
+            Node::LowBattery {} => condition(view.low_battery),
+            Node::AtBase {} => condition(view.is_at_base()),
+
+            Node::Gather {} => {
+                if view.can_work_here {
+                    Status::Running(Action::Stay)
+                } else {
+                    Status::Failure
+                }
+            }
+            Node::MoveToTask {} => move_to(view.task),
+            Node::MoveToNearest { targets } => move_to(nearest_target(view, knowledge, targets)),
+            Node::Explore {} => move_to(best_frontier_target(view, knowledge)),
+            Node::MoveToSwarm {} => move_to(swarm_centroid(&view.peers)),
+            Node::ReturnToBase {} => {
+                if view.is_at_base() {
+                    Status::Success
+                } else {
+                    Status::Running(Action::ReturnToBase)
+                }
+            }
+            Node::Wait {} => Status::Running(Action::Stay),
+            Node::Wander {} => Status::Running(Action::Wander),
+            Node::Builtin { role } => Status::Running(role.decide(view, knowledge)),
+        }
+    }
+}
+
+fn condition(holds: bool) -> Status {
+    if holds {
+        Status::Success
+    } else {
+        Status::Failure
+    }
+}
+
+fn move_to(target: Option<(usize, usize)>) -> Status {
+    match target {
+        Some((x, y)) => Status::Running(Action::MoveTo { x, y }),
+        None => Status::Failure,
+    }
+}
+
+fn nearest_target(
+    view: &RobotView,
+    knowledge: &KnowledgeMap,
+    targets: &[Target],
+) -> Option<(usize, usize)> {
+    targets
+        .iter()
+        .filter_map(|target| match target {
+            Target::Mineral if view.has_capability(Capability::Mine) => {
+                find_nearest_resource(view.x, view.y, knowledge, CellType::Mineral, &view.reserved)
+            }
+            Target::Energy if view.has_capability(Capability::HarvestEnergy) => {
+                find_nearest_resource(view.x, view.y, knowledge, CellType::Energy, &view.reserved)
+            }
+            Target::Site => find_sampling_site(view, knowledge),
+            _ => None,
+        })
+        .min_by_key(|&(x, y)| view.x.abs_diff(x).pow(2) + view.y.abs_diff(y).pow(2))
+}
+
+/// A behaviour driven by a tree. Each robot gets its own copy, so `Repeat`
+/// counters are never shared.
+#[derive(Debug, Clone)]
+pub struct BehaviorTree {
+    pub root: Node,
+}
+
+impl Behavior for BehaviorTree {
+    fn decide(&self, view: &RobotView, knowledge: &KnowledgeMap) -> Action {
+        match self.root.tick(view, knowledge) {
+            Status::Running(action) => action,
+            Status::Success | Status::Failure => Action::Stay,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TreeLoadError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    MissingRole(&'static str),
+}
+
+impl fmt::Display for TreeLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeLoadError::Io(err) => write!(f, "cannot read behaviour trees: {}", err),
+            TreeLoadError::Parse(err) => write!(f, "invalid behaviour tree: {}", err),
+            TreeLoadError::MissingRole(role) => {
+                write!(f, "no behaviour tree for role \"{}\"", role)
+            }
+        }
+    }
+}
+
+/// Behaviour trees by role, as described in a JSON file mapping each role
+/// name to its root node.
+#[derive(Debug, Clone)]
+pub struct TreeLibrary {
+    trees: HashMap<String, Node>,
+}
+
+impl Default for TreeLibrary {
+    fn default() -> Self {
+        Self::from_json(DEFAULT_TREES).expect("bundled behaviour trees are valid")
+    }
+}
+
+impl TreeLibrary {
+    pub fn from_json(text: &str) -> Result<Self, TreeLoadError> {
+        let trees: HashMap<String, Node> =
+            serde_json::from_str(text).map_err(TreeLoadError::Parse)?;
+
+        if let Some(role) = Role::ALL
+            .into_iter()
+            .find(|role| !trees.contains_key(role.name()))
+        {
+            return Err(TreeLoadError::MissingRole(role.name()));
+        }
+
+        Ok(Self { trees })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TreeLoadError> {
+        let text = std::fs::read_to_string(path).map_err(TreeLoadError::Io)?;
+        Self::from_json(&text)
+    }
+
+    /// A fresh copy of the tree for the role matching `modules`.
+    pub fn behavior_for(&self, modules: &[RobotModule]) -> Box<dyn Behavior> {
+        let root = &self.trees[Role::of(modules).name()];
+        Box::new(BehaviorTree { root: root.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::map::SiteKind;
+
+    fn test_view() -> RobotView<'static> {
+        RobotView {
+            x: 10,
+            y: 10,
+            modules: &[RobotModule::Drill],
+            base: (25, 15),
+            cargo_load: 0,
+            cargo_full: false,
+            low_battery: false,
+            can_work_here: false,
+            task: None,
+            reserved: Vec::new(),
+            peers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn bundled_trees_cover_every_role() {
+        let library = TreeLibrary::default();
+        for role in Role::ALL {
+            assert!(library.trees.contains_key(role.name()));
+        }
+        assert_eq!(
+            Role::of(&[RobotModule::EnergyCollector, RobotModule::Drill]),
+            Role::Miner
+        );
+        assert_eq!(Role::of(&[RobotModule::Imager]), Role::Collector);
+    }
+
+    #[test]
+    fn bundled_trees_run_errands_before_looking_for_work() {
+        let knowledge = KnowledgeMap::new(50, 30);
+        let miner = TreeLibrary::default().behavior_for(&[RobotModule::Drill]);
+        let mut view = test_view();
+
+        view.can_work_here = true;
+        assert_eq!(miner.decide(&view, &knowledge), Action::Stay);
+
+        view.cargo_full = true;
+        assert_eq!(miner.decide(&view, &knowledge), Action::ReturnToBase);
+
+        view.cargo_full = false;
+        view.low_battery = true;
+        (view.x, view.y) = view.base;
+        assert_eq!(miner.decide(&view, &knowledge), Action::Stay);
+    }
+
+    #[test]
+    fn bundled_trees_fall_back_by_role_with_no_work_known() {
+        let library = TreeLibrary::default();
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        for y in 12..=16 {
+            for x in 13..=17 {
+                knowledge.observe(x, y, CellType::Empty, 0);
+            }
+        }
+
+        let mut view = test_view();
+        view.peers = vec![(20, 10), (30, 20)];
+        let miner = library.behavior_for(&[RobotModule::Drill]);
+        assert_eq!(miner.decide(&view, &knowledge), Action::Wander);
+
+        let relay = library.behavior_for(&[RobotModule::CommsRelay]);
+        assert_eq!(
+            relay.decide(&view, &knowledge),
+            Action::MoveTo { x: 25, y: 15 }
+        );
+
+        (view.x, view.y) = (15, 14);
+        let explorer = library.behavior_for(&[RobotModule::Exploration]);
+        assert!(matches!(
+            explorer.decide(&view, &knowledge),
+            Action::MoveTo { .. }
+        ));
+    }
+
+    #[test]
+    fn move_to_task_heads_for_the_claimed_cell() {
+        let knowledge = KnowledgeMap::new(50, 30);
+        let mut view = test_view();
+        assert_eq!(Node::MoveToTask {}.tick(&view, &knowledge), Status::Failure);
+
+        view.task = Some((3, 4));
+        assert_eq!(
+            Node::MoveToTask {}.tick(&view, &knowledge),
+            Status::Running(Action::MoveTo { x: 3, y: 4 })
+        );
+    }
+
+    #[test]
+    fn move_to_nearest_picks_the_closest_known_target() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        knowledge.observe(14, 10, CellType::Mineral, 0);
+        knowledge.observe(11, 12, CellType::Mineral, 0);
+        knowledge.observe(10, 11, CellType::Energy, 0);
+        let mut view = test_view();
+        let node = Node::MoveToNearest {
+            targets: vec![Target::Mineral, Target::Energy],
+        };
+
+        assert_eq!(
+            node.tick(&view, &knowledge),
+            Status::Running(Action::MoveTo { x: 11, y: 12 }),
+            "drills leave energy alone"
+        );
+
+        view.reserved = vec![(11, 12)];
+        assert_eq!(
+            node.tick(&view, &knowledge),
+            Status::Running(Action::MoveTo { x: 14, y: 10 })
+        );
+    }
+
+    #[test]
+    fn move_to_nearest_weighs_sites_by_the_kind_seen_there() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        knowledge.observe_site(12, 10, SiteKind::Chemical, 0);
+        knowledge.observe_site(15, 10, SiteKind::Geological, 0);
+        let node = Node::MoveToNearest {
+            targets: vec![Target::Site],
+        };
+
+        assert_eq!(
+            node.tick(&test_view(), &knowledge),
+            Status::Running(Action::MoveTo { x: 15, y: 10 })
+        );
+    }
+
+    #[test]
+    fn explore_plans_around_the_obstacles_the_robot_knows_of() {
+        // A corridor of known ground, unknown at both ends.
+        let mut knowledge = KnowledgeMap::new(20, 1);
+        for x in 5..=15 {
+            knowledge.observe(x, 0, CellType::Empty, 0);
+        }
+        let mut view = test_view();
+        (view.x, view.y) = (9, 0);
+
+        assert_eq!(
+            Node::Explore {}.tick(&view, &knowledge),
+            Status::Running(Action::MoveTo { x: 5, y: 0 })
+        );
+
+        knowledge.observe(7, 0, CellType::Obstacle, 0);
+        assert_eq!(
+            Node::Explore {}.tick(&view, &knowledge),
+            Status::Running(Action::MoveTo { x: 15, y: 0 })
+        );
+    }
+
+    #[test]
+    fn composites_follow_child_statuses() {
+        let knowledge = KnowledgeMap::new(50, 30);
+        let mut view = test_view();
+        view.cargo_full = true;
+
+        let tree: Node = serde_json::from_str(
+            r#"{ "node": "selector", "children": [
+                { "node": "sequence", "children": [
+                    { "node": "inverter", "child": { "node": "cargo_full" } },
+                    { "node": "wander" }
+                ] },
+                { "node": "return_to_base" }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tree.tick(&view, &knowledge),
+            Status::Running(Action::ReturnToBase)
+        );
+        view.cargo_full = false;
+        assert_eq!(
+            tree.tick(&view, &knowledge),
+            Status::Running(Action::Wander)
+        );
+    }
+
+    #[test]
+    fn repeat_runs_until_its_count_is_reached() {
+        let knowledge = KnowledgeMap::new(50, 30);
+        let mut view = test_view();
+        let tree: Node = serde_json::from_str(
+            r#"{ "node": "sequence", "children": [
+                { "node": "repeat", "times": 2, "child": { "node": "return_to_base" } },
+                { "node": "wander" }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tree.tick(&view, &knowledge),
+            Status::Running(Action::ReturnToBase)
+        );
+        // Both runs end at the base within the tick, so no tick is lost.
+        (view.x, view.y) = view.base;
+        assert_eq!(
+            tree.tick(&view, &knowledge),
+            Status::Running(Action::Wander)
+        );
+        assert_eq!(
+            tree.tick(&view, &knowledge),
+            Status::Running(Action::Wander),
+            "counts over"
+        );
+    }
+
+    #[test]
+    fn repeat_without_a_count_runs_until_its_child_fails() {
+        let knowledge = KnowledgeMap::new(50, 30);
+        let mut view = test_view();
+        let tree: Node = serde_json::from_str(
+            r#"{ "node": "sequence", "children": [
+                { "node": "repeat",
+                  "child": { "node": "inverter", "child": { "node": "has_cargo" } } },
+                { "node": "wander" }
+            ] }"#,
+        )
+        .unwrap();
+
+        for _ in 0..5 {
+            assert_eq!(tree.tick(&view, &knowledge), Status::Running(Action::Stay));
+        }
+        view.cargo_load = 1;
+        assert_eq!(tree.tick(&view, &knowledge), Status::Failure);
+    }
+
+    #[test]
+    fn misspelt_fields_are_rejected() {
+        let typo = serde_json::from_str::<Node>(
+            r#"{ "node": "repeat", "time": 3, "child": { "node": "wander" } }"#,
+        )
+        .unwrap_err();
+        assert!(typo.to_string().contains("time"), "{typo}");
+
+        let library = r#"{
+            "explorer": { "node": "wander" },
+            "miner": { "node": "gather", "target": "mineral" },
+            "collector": { "node": "wander" },
+            "relay": { "node": "wander" },
+            "hauler": { "node": "wander" }
+        }"#;
+        assert!(matches!(
+            TreeLibrary::from_json(library),
+            Err(TreeLoadError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn libraries_must_define_every_role() {
+        let err = TreeLibrary::from_json(r#"{ "explorer": { "node": "wander" } }"#).unwrap_err();
+        assert!(matches!(err, TreeLoadError::MissingRole("hauler")));
+        assert!(matches!(
+            TreeLibrary::from_json(r#"{ "miner": { "node": "fly" } }"#),
+            Err(TreeLoadError::Parse(_))
+        ));
+    }
+}
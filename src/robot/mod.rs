@@ -1,16 +1,268 @@
-use crate::environment::map::CellType;
-use crate::environment::Map;
+pub mod behavior;
+pub mod behavior_tree;
+
+use self::behavior::{Action, Behavior, RobotView};
+use crate::environment::map::{CellType, SiteKind};
+use crate::environment::{KnowledgeCommit, KnowledgeMap, Map};
 use crate::pathfinding;
-use crate::station::Station;
+use crate::simulation::ResourceType;
+use crate::station::contract_net::{self, Bid};
+use crate::station::logistics::Haul;
+use crate::station::tasks::{Task, TaskMessage};
+use crate::station::{ScienceTally, Station};
 use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum RobotModule {
     Exploration,
     Drill,
     EnergyCollector,
+    /// Grades samples in situ, so each one brings back more data.
+    ChemicalAnalyzer,
+    /// High-resolution camera widening the robot's sensor radius.
+    Imager,
+    /// Lets nearby robots sync knowledge without going home.
+    CommsRelay,
+    /// Large hold for carrying stock from one station to another.
+    CargoBay,
+}
+
+/// What a robot can do, given the modules it carries. Behaviour is chosen
+/// from the union of its modules' capabilities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capability {
+    Explore,
+    Mine,
+    HarvestEnergy,
+    Sample(SiteKind),
+    GradeSamples,
+    WideSensors,
+    Relay,
+    Haul,
+}
+
+impl RobotModule {
+    pub const ALL: [RobotModule; 7] = [
+        RobotModule::Exploration,
+        RobotModule::Drill,
+        RobotModule::EnergyCollector,
+        RobotModule::ChemicalAnalyzer,
+        RobotModule::Imager,
+        RobotModule::CommsRelay,
+        RobotModule::CargoBay,
+    ];
+
+    pub fn capabilities(&self) -> &'static [Capability] {
+        match self {
+            RobotModule::Exploration => &[
+                Capability::Explore,
+                Capability::Sample(SiteKind::Chemical),
+                Capability::Sample(SiteKind::Biosignature),
+            ],
+            RobotModule::Drill => &[Capability::Mine, Capability::Sample(SiteKind::Geological)],
+            RobotModule::EnergyCollector => &[Capability::HarvestEnergy],
+            RobotModule::ChemicalAnalyzer => &[
+                Capability::Sample(SiteKind::Chemical),
+                Capability::GradeSamples,
+            ],
+            RobotModule::Imager => &[Capability::WideSensors],
+            RobotModule::CommsRelay => &[Capability::Relay],
+            RobotModule::CargoBay => &[Capability::Haul],
+        }
+    }
+
+    /// Mass of the module. The chassis carries `CHASSIS_FREE_WEIGHT` for
+    /// free; every unit above costs extra battery per move.
+    pub fn weight(&self) -> u32 {
+        match self {
+            RobotModule::Exploration => 1,
+            RobotModule::Drill => 3,
+            RobotModule::EnergyCollector => 2,
+            RobotModule::ChemicalAnalyzer => 2,
+            RobotModule::Imager => 1,
+            RobotModule::CommsRelay => 2,
+            RobotModule::CargoBay => 3,
+        }
+    }
+
+    /// Name of the module on the command line and in scenario files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RobotModule::Exploration => "exploration",
+            RobotModule::Drill => "drill",
+            RobotModule::EnergyCollector => "energy-collector",
+            RobotModule::ChemicalAnalyzer => "chemical-analyzer",
+            RobotModule::Imager => "imager",
+            RobotModule::CommsRelay => "comms-relay",
+            RobotModule::CargoBay => "cargo-bay",
+        }
+    }
+
+    /// Extra battery drawn by the module for every cell the robot moves.
+    pub fn energy_drain(&self) -> u32 {
+        match self {
+            RobotModule::Exploration => 1,
+            RobotModule::Drill => 2,
+            RobotModule::EnergyCollector => 0,
+            RobotModule::ChemicalAnalyzer => 1,
+            RobotModule::Imager => 1,
+            RobotModule::CommsRelay => 1,
+            RobotModule::CargoBay => 1,
+        }
+    }
 }
 
+/// Units of cargo each module adds to a robot's hold. Scenarios only list
+/// the modules they change.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CargoCapacities {
+    pub exploration: u32,
+    pub drill: u32,
+    pub energy_collector: u32,
+    pub chemical_analyzer: u32,
+    pub imager: u32,
+    pub comms_relay: u32,
+    pub cargo_bay: u32,
+}
+
+impl Default for CargoCapacities {
+    fn default() -> Self {
+        Self {
+            exploration: 1,
+            drill: 5,
+            energy_collector: 3,
+            chemical_analyzer: 2,
+            imager: 0,
+            comms_relay: 0,
+            cargo_bay: 10,
+        }
+    }
+}
+
+impl CargoCapacities {
+    pub fn of(&self, module: &RobotModule) -> u32 {
+        match module {
+            RobotModule::Exploration => self.exploration,
+            RobotModule::Drill => self.drill,
+            RobotModule::EnergyCollector => self.energy_collector,
+            RobotModule::ChemicalAnalyzer => self.chemical_analyzer,
+            RobotModule::Imager => self.imager,
+            RobotModule::CommsRelay => self.comms_relay,
+            RobotModule::CargoBay => self.cargo_bay,
+        }
+    }
+
+    /// Hold of a robot carrying `modules`.
+    pub fn robot(&self, modules: &[RobotModule]) -> u32 {
+        modules.iter().map(|module| self.of(module)).sum()
+    }
+}
+
+/// Checks that `modules` can be fitted on one chassis: at least one module,
+/// none twice, and no heavier than `MAX_PAYLOAD_WEIGHT` together.
+pub fn check_loadout(modules: &[RobotModule]) -> Result<(), String> {
+    if modules.is_empty() {
+        return Err("a robot needs at least one module".to_string());
+    }
+    if let Some(module) = modules
+        .iter()
+        .enumerate()
+        .find_map(|(i, module)| modules[..i].contains(module).then_some(module))
+    {
+        return Err(format!("module \"{}\" is fitted twice", module.name()));
+    }
+    let weight: u32 = modules.iter().map(RobotModule::weight).sum();
+    if weight > MAX_PAYLOAD_WEIGHT {
+        return Err(format!(
+            "modules weigh {} together, a chassis carries at most {}",
+            weight, MAX_PAYLOAD_WEIGHT
+        ));
+    }
+    Ok(())
+}
+
+impl FromStr for RobotModule {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        RobotModule::ALL
+            .into_iter()
+            .find(|module| module.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = RobotModule::ALL.iter().map(|m| m.name()).collect();
+                format!(
+                    "unknown module \"{}\", expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// What a robot is busy with. Settled every tick from the action its
+/// behaviour picked, except `Broken`, which is final.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RobotState {
+    Idle,
+    /// Heading for ground it has not seen, or wandering.
+    Exploring,
+    /// Heading for a known target.
+    Travelling,
+    Harvesting,
+    Returning,
+    /// Waiting at the base for the station to top its battery up.
+    Charging,
+    /// Out of battery away from the base.
+    Stranded,
+    /// Broke down in the field and will not move again.
+    Broken,
+}
+
+impl RobotState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RobotState::Idle => "idle",
+            RobotState::Exploring => "exploring",
+            RobotState::Travelling => "travelling",
+            RobotState::Harvesting => "harvesting",
+            RobotState::Returning => "returning",
+            RobotState::Charging => "charging",
+            RobotState::Stranded => "stranded",
+            RobotState::Broken => "broken",
+        }
+    }
+}
+
+/// How far a robot's sensors reach, in cells.
+pub const SENSOR_RADIUS: i32 = 2;
+/// Extra sensor reach granted by an imager.
+const IMAGER_RADIUS_BONUS: i32 = 2;
+/// Data points a graded sample is worth.
+const GRADED_SAMPLE_VALUE: u32 = 2;
+/// Manhattan distance over which a comms relay reaches other robots.
+pub const RELAY_RANGE: usize = 6;
+
+pub const BATTERY_CAPACITY: u32 = 200;
+/// Battery drawn by the chassis for every cell moved, before module drain.
+const BASE_MOVE_COST: u32 = 1;
+/// Module weight the chassis moves without extra cost.
+pub const CHASSIS_FREE_WEIGHT: u32 = 3;
+/// Heaviest module set a chassis can be built with.
+pub const MAX_PAYLOAD_WEIGHT: u32 = 6;
+const GATHER_COST: u32 = 2;
+/// Ticks spent on a deposit to extract one unit.
+pub const EXTRACTION_TICKS: u32 = 3;
+/// Battery restored by one unit of the station's energy storage: a full
+/// charge, so that harvesting leaves energy over for building.
+pub const BATTERY_PER_ENERGY_UNIT: u32 = 200;
+/// Spare cells of travel a robot keeps in hand when deciding to head home.
+const RETURN_MARGIN_STEPS: u32 = 5;
+
 #[derive(Debug)]
 pub struct Robot {
     pub id: usize,
@@ -18,170 +270,710 @@ pub struct Robot {
     pub y: usize,
     pub modules: Vec<RobotModule>,
     pub has_data_to_share: bool,
+    /// What this robot has seen itself or pulled from the station.
+    pub knowledge: KnowledgeMap,
 
     pub carried_energy: u32,
     pub carried_minerals: u32,
-    pub carried_scientific_data: u32,
+    pub carried_scientific_data: ScienceTally,
+    /// Units the robot carries before heading home. Defaults to the sum of
+    /// its modules' capacities.
+    pub cargo_capacity: u32,
+    pub battery: u32,
+    /// Decides what the robot does each tick. Defaults to the behaviour
+    /// matching its modules and can be swapped at any time.
+    pub behavior: Box<dyn Behavior>,
+    pub state: RobotState,
+    /// Station that built the robot, or that it started from. Its tasks
+    /// come from that station's board.
+    pub home: usize,
+    /// Transfer the robot is carrying out, for haulers.
+    pub haul: Option<Haul>,
+    /// Task claimed on the station's board, if any.
+    pub task: Option<Task>,
+    /// Odds that the robot breaks down for good on any one move.
+    pub breakdown_chance: f64,
+    /// Messages from the station, read when the robot next decides.
+    inbox: Vec<TaskMessage>,
+    extraction_progress: u32,
     last_dx: i32,
     last_dy: i32,
+    rng: ChaCha8Rng,
 }
 
 impl Robot {
-    pub fn new(id: usize, x: usize, y: usize, modules: Vec<RobotModule>) -> Self {
+    /// `knowledge` is the map the robot starts with, usually a copy of the
+    /// station's. `rng` is the robot's own sub-stream of the simulation RNG,
+    /// so its decisions stay reproducible regardless of how many robots exist.
+    pub fn new(
+        id: usize,
+        x: usize,
+        y: usize,
+        modules: Vec<RobotModule>,
+        knowledge: KnowledgeMap,
+        rng: ChaCha8Rng,
+    ) -> Self {
+        let cargo_capacity = CargoCapacities::default().robot(&modules);
+        let behavior = behavior::default_behavior(&modules);
+
         Self {
             id,
             x,
             y,
             modules,
             has_data_to_share: false,
+            knowledge,
             carried_energy: 0,
             carried_minerals: 0,
-            carried_scientific_data: 0,
+            carried_scientific_data: ScienceTally::default(),
+            cargo_capacity,
+            battery: BATTERY_CAPACITY,
+            behavior,
+            state: RobotState::Idle,
+            home: 0,
+            haul: None,
+            task: None,
+            breakdown_chance: 0.0,
+            inbox: Vec::new(),
+            extraction_progress: 0,
             last_dx: 0,
             last_dy: 0,
+            rng,
         }
     }
 
+    pub fn cargo_load(&self) -> u32 {
+        self.carried_energy + self.carried_minerals + self.carried_scientific_data.total()
+    }
+
+    /// Robots without a hold, such as relays, are never full.
+    pub fn is_cargo_full(&self) -> bool {
+        self.cargo_capacity > 0 && self.cargo_load() >= self.cargo_capacity
+    }
+
     pub fn should_return_to_base(&self) -> bool {
-        self.carried_energy > 0 || self.carried_minerals > 0 || self.carried_scientific_data > 0
+        self.is_cargo_full()
+    }
+
+    /// Battery spent to move one cell.
+    pub fn move_cost(&self) -> u32 {
+        let drain: u32 = self.modules.iter().map(|m| m.energy_drain()).sum();
+        BASE_MOVE_COST + drain + self.payload_weight().saturating_sub(CHASSIS_FREE_WEIGHT)
+    }
+
+    pub fn payload_weight(&self) -> u32 {
+        self.modules.iter().map(|m| m.weight()).sum()
+    }
+
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        self.modules
+            .iter()
+            .any(|m| m.capabilities().contains(&capability))
+    }
+
+    /// Haulers carry stock between stations rather than gathering it.
+    pub fn is_hauler(&self) -> bool {
+        self.has_capability(Capability::Haul)
+    }
+
+    /// A stranded robot cannot afford a single move and waits for a recharge.
+    pub fn is_stranded(&self) -> bool {
+        self.battery < self.move_cost()
+    }
+
+    /// True once the remaining charge only just covers the trip back to the
+    /// base. The Manhattan distance is padded because paths detour around
+    /// obstacles.
+    pub fn needs_recharge(&self, base_x: usize, base_y: usize) -> bool {
+        self.battery <= self.return_trip_cost(self.x, self.y, base_x, base_y)
+    }
+
+    /// Battery this robot should keep to get from (`x`, `y`) back to the
+    /// base, margin included.
+    pub fn return_trip_cost(&self, x: usize, y: usize, base_x: usize, base_y: usize) -> u32 {
+        let distance = (x.abs_diff(base_x) + y.abs_diff(base_y)) as u32;
+        let trip_steps = distance * 3 / 2 + RETURN_MARGIN_STEPS;
+        trip_steps * self.move_cost()
+    }
+
+    /// Tops the battery up from the station's energy storage. Whatever part
+    /// of the last unit the battery cannot take stays with the station for
+    /// the next robot.
+    pub fn recharge(&mut self, station: &mut Station) {
+        self.battery += station.draw_battery(BATTERY_CAPACITY - self.battery);
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.state == RobotState::Broken
+    }
+
+    fn step_to(&mut self, x: usize, y: usize) {
+        self.x = x;
+        self.y = y;
+        self.extraction_progress = 0;
+        self.battery = self.battery.saturating_sub(self.move_cost());
+        if self.breakdown_chance > 0.0 && self.rng.gen_bool(self.breakdown_chance) {
+            self.state = RobotState::Broken;
+        }
+    }
+
+    pub fn sensor_radius(&self) -> i32 {
+        if self.has_capability(Capability::WideSensors) {
+            SENSOR_RADIUS + IMAGER_RADIUS_BONUS
+        } else {
+            SENSOR_RADIUS
+        }
+    }
+
+    /// Records the cells around the robot in its private knowledge map.
+    pub fn observe(&mut self, map: &Map, step: usize) {
+        let radius = self.sensor_radius();
+        if self
+            .knowledge
+            .observe_area(map, self.x, self.y, radius, step)
+        {
+            self.has_data_to_share = true;
+        }
     }
 
-    pub fn move_towards(&mut self, target_x: usize, target_y: usize, map: &Map) {
+    /// Takes one step along the cheapest way to the target that the robot's
+    /// own knowledge shows. Rock it runs into unawares is recorded as seen at
+    /// `step` and the way planned again.
+    pub fn move_towards(&mut self, target_x: usize, target_y: usize, map: &Map, step: usize) {
+        if self.is_stranded() {
+            return;
+        }
+
         let start = (self.x, self.y);
         let goal = (target_x, target_y);
 
-        if let Some(path) = pathfinding::find_path(map, start, goal) {
-            if path.len() > 1 {
-                let next_pos = path[1];
-                self.x = next_pos.0;
-                self.y = next_pos.1;
+        while let Some(path) = pathfinding::find_path(&self.knowledge, start, goal) {
+            let Some(&(next_x, next_y)) = path.get(1) else {
+                return;
+            };
+            if map.is_walkable(next_x, next_y) {
+                self.step_to(next_x, next_y);
+                return;
             }
+            self.knowledge
+                .observe(next_x, next_y, CellType::Obstacle, step);
+            self.has_data_to_share = true;
+        }
+
+        let dx = if self.x < target_x {
+            1
+        } else if self.x > target_x {
+            -1
         } else {
-            let dx = if self.x < target_x {
-                1
-            } else if self.x > target_x {
-                -1
-            } else {
-                0
-            };
-            let dy = if self.y < target_y {
-                1
-            } else if self.y > target_y {
-                -1
-            } else {
-                0
-            };
+            0
+        };
+        let dy = if self.y < target_y {
+            1
+        } else if self.y > target_y {
+            -1
+        } else {
+            0
+        };
 
-            let new_x = (self.x as isize + dx).clamp(0, map.config.width as isize - 1) as usize;
-            let new_y = (self.y as isize + dy).clamp(0, map.config.height as isize - 1) as usize;
+        let new_x = (self.x as isize + dx).clamp(0, map.config.width as isize - 1) as usize;
+        let new_y = (self.y as isize + dy).clamp(0, map.config.height as isize - 1) as usize;
 
-            if map.is_walkable(new_x, new_y) {
-                self.x = new_x;
-                self.y = new_y;
+        if map.is_walkable(new_x, new_y) {
+            self.step_to(new_x, new_y);
+        }
+    }
+
+    /// Queues a message from the station. Broken robots hear nothing.
+    pub fn receive(&mut self, message: TaskMessage) {
+        if !self.is_broken() {
+            self.inbox.push(message);
+        }
+    }
+
+    /// Reads the station's messages: takes on the task awarded, if any, and
+    /// otherwise prices the tasks put up for bids from what it knows of the
+    /// way there and back to `base`. Returns the bids to send back.
+    pub fn answer_station(&mut self, base: (usize, usize)) -> Vec<Bid> {
+        let mut announced = Vec::new();
+        for message in std::mem::take(&mut self.inbox) {
+            match message {
+                TaskMessage::Award { task, .. } => self.task = Some(task),
+                TaskMessage::CallForBids { task, .. } => announced.push(task),
+                TaskMessage::Bid(_) => {}
             }
         }
+
+        if self.task.is_some() || self.is_broken() {
+            return Vec::new();
+        }
+        contract_net::bids_for(self, &announced, base)
+    }
+
+    /// Asks the robot's behaviour what to do this tick. `swarm` may include
+    /// the robot itself, and `claimed` lists claimed cells with the robot
+    /// holding each claim.
+    pub fn decide(
+        &self,
+        map: &Map,
+        base: (usize, usize),
+        swarm: &[Robot],
+        claimed: &[((usize, usize), usize)],
+    ) -> Action {
+        let view = RobotView {
+            x: self.x,
+            y: self.y,
+            modules: &self.modules,
+            base,
+            cargo_load: self.cargo_load(),
+            cargo_full: self.is_cargo_full(),
+            low_battery: self.needs_recharge(base.0, base.1),
+            can_work_here: self.can_work_here(map),
+            task: self.task.map(|task| (task.x, task.y)),
+            reserved: claimed
+                .iter()
+                .filter(|&&(_, robot_id)| robot_id != self.id)
+                .map(|&(cell, _)| cell)
+                .collect(),
+            peers: swarm
+                .iter()
+                .filter(|r| r.id != self.id)
+                .map(|r| (r.x, r.y))
+                .collect(),
+        };
+        self.behavior.decide(&view, &self.knowledge)
     }
 
-    pub fn is_near_base(&self, center_x: usize, center_y: usize) -> bool {
-        let dx = self.x.abs_diff(center_x);
-        let dy = self.y.abs_diff(center_y);
+    /// Carries out `action` for tick `step`, updating the robot's state.
+    /// `base` is the station the robot answers to. Broken robots do nothing.
+    pub fn act(&mut self, action: Action, map: &Map, base: (usize, usize), step: usize) {
+        if self.is_broken() {
+            return;
+        }
+
+        self.state = self.state_for(action, map, base);
+        match action {
+            Action::Stay => {}
+            Action::MoveTo { x, y } => self.move_towards(x, y, map, step),
+            Action::ReturnToBase => self.move_towards(base.0, base.1, map, step),
+            Action::Wander => self.random_move(map, base, step),
+        }
+    }
+
+    fn state_for(&self, action: Action, map: &Map, base: (usize, usize)) -> RobotState {
+        let (base_x, base_y) = base;
+        let at_base = self.is_near_base(base_x, base_y);
+
+        if self.is_stranded() {
+            return if at_base {
+                RobotState::Charging
+            } else {
+                RobotState::Stranded
+            };
+        }
+
+        match action {
+            Action::Stay if self.can_work_here(map) => RobotState::Harvesting,
+            Action::Stay if at_base && self.needs_recharge(base_x, base_y) => RobotState::Charging,
+            Action::Stay => RobotState::Idle,
+            Action::MoveTo { x, y } if self.knowledge.is_known(x, y) => RobotState::Travelling,
+            Action::MoveTo { .. } | Action::Wander => RobotState::Exploring,
+            Action::ReturnToBase => RobotState::Returning,
+        }
+    }
+
+    pub fn is_within_relay_range(&self, other: &Robot) -> bool {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y) <= RELAY_RANGE
+    }
+
+    pub fn is_near_base(&self, base_x: usize, base_y: usize) -> bool {
+        let dx = self.x.abs_diff(base_x);
+        let dy = self.y.abs_diff(base_y);
         dx <= 1 && dy <= 1
     }
 
-    pub fn random_move(&mut self, map: &Map) {
-        let center_x = map.config.width / 2;
-        let center_y = map.config.height / 2;
+    pub fn random_move(&mut self, map: &Map, (base_x, base_y): (usize, usize), step: usize) {
+        if self.is_stranded() {
+            return;
+        }
 
         if self.should_return_to_base() {
-            if !self.is_near_base(center_x, center_y) {
-                self.move_towards(center_x, center_y, map);
+            if !self.is_near_base(base_x, base_y) {
+                self.move_towards(base_x, base_y, map, step);
             }
         } else {
-            let mut rng = rand::thread_rng();
-
-            if rng.gen_bool(0.8) && (self.last_dx != 0 || self.last_dy != 0) {
+            if self.rng.gen_bool(0.8) && (self.last_dx != 0 || self.last_dy != 0) {
                 let new_x =
                     (self.x as i32 + self.last_dx).clamp(0, map.config.width as i32 - 1) as usize;
                 let new_y =
                     (self.y as i32 + self.last_dy).clamp(0, map.config.height as i32 - 1) as usize;
 
                 if map.is_walkable(new_x, new_y) {
-                    self.x = new_x;
-                    self.y = new_y;
+                    self.step_to(new_x, new_y);
                     return;
                 }
             }
 
-            let dx = rng.gen_range(-1..=1);
-            let dy = rng.gen_range(-1..=1);
+            let dx = self.rng.gen_range(-1..=1);
+            let dy = self.rng.gen_range(-1..=1);
 
             let new_x = (self.x as i32 + dx).clamp(0, map.config.width as i32 - 1) as usize;
             let new_y = (self.y as i32 + dy).clamp(0, map.config.height as i32 - 1) as usize;
 
             if map.is_walkable(new_x, new_y) {
-                self.x = new_x;
-                self.y = new_y;
+                self.step_to(new_x, new_y);
                 self.last_dx = dx;
                 self.last_dy = dy;
             }
         }
     }
 
-    pub fn try_gather_resource(&mut self, map: &mut Map) -> bool {
-        if self.x >= map.config.width || self.y >= map.config.height {
+    pub fn can_sample(&self, kind: SiteKind) -> bool {
+        self.has_capability(Capability::Sample(kind))
+    }
+
+    /// Whether the robot can gather from the cell it stands on, in which case
+    /// it should stay put until done.
+    pub fn can_work_here(&self, map: &Map) -> bool {
+        if self.battery < GATHER_COST || self.is_cargo_full() || self.is_broken() {
             return false;
         }
 
         match map.cells[self.y][self.x] {
-            CellType::Energy => {
-                if self.modules.contains(&RobotModule::EnergyCollector) {
-                    self.carried_energy += 1;
-                    map.cells[self.y][self.x] = CellType::Empty;
-                    return true;
-                }
+            CellType::Energy => self.has_capability(Capability::HarvestEnergy),
+            CellType::Mineral => self.has_capability(Capability::Mine),
+            CellType::ScientificSite => {
+                map.site_kinds[self.y][self.x].is_some_and(|kind| self.can_sample(kind))
             }
-            CellType::Mineral => {
-                if self.modules.contains(&RobotModule::Drill) {
-                    self.carried_minerals += 1;
-                    map.cells[self.y][self.x] = CellType::Empty;
-                    return true;
-                }
+            _ => false,
+        }
+    }
+
+    /// Works the cell under the robot for one tick. Returns what was gathered,
+    /// and how much, if a unit or sample was completed this tick.
+    pub fn try_gather_resource(&mut self, map: &mut Map) -> Option<(ResourceType, u32)> {
+        if self.x >= map.config.width || self.y >= map.config.height {
+            return None;
+        }
+
+        if !self.can_work_here(map) {
+            return None;
+        }
+
+        let cell = map.cells[self.y][self.x];
+        match cell {
+            CellType::Energy if self.work_cell(map, EXTRACTION_TICKS) => {
+                self.carried_energy += 1;
+                Some((ResourceType::Energy, 1))
+            }
+            CellType::Mineral if self.work_cell(map, EXTRACTION_TICKS) => {
+                self.carried_minerals += 1;
+                Some((ResourceType::Mineral, 1))
             }
             CellType::ScientificSite => {
-                if self.modules.contains(&RobotModule::Exploration) {
-                    self.carried_scientific_data += 1;
-                    return true;
+                let kind = map.site_kinds[self.y][self.x]?;
+                if !self.work_cell(map, kind.sampling_ticks()) {
+                    return None;
                 }
+                let value = if self.has_capability(Capability::GradeSamples) {
+                    GRADED_SAMPLE_VALUE
+                } else {
+                    1
+                };
+                self.carried_scientific_data.add(kind, value);
+                Some((ResourceType::ScientificData(kind), value))
             }
-            _ => {}
+            _ => None,
+        }
+    }
+
+    /// Spends a tick on the deposit or site under the robot. Returns true when
+    /// the `ticks`-th tick completes a unit.
+    fn work_cell(&mut self, map: &mut Map, ticks: u32) -> bool {
+        self.extraction_progress += 1;
+        if self.extraction_progress < ticks {
+            return false;
         }
 
-        false
+        self.extraction_progress = 0;
+        self.battery -= GATHER_COST;
+        map.extract_unit(self.x, self.y)
     }
 
-    pub fn try_deposit_resources(&mut self, station: &mut Station, map: &Map) {
-        let center_x = map.config.width / 2;
-        let center_y = map.config.height / 2;
+    /// Unloads cargo and recharges at the station, then syncs knowledge with
+    /// it: new observations are pushed and merged, then the station's map is
+    /// pulled. Returns the commit recorded for the push, if there was one.
+    pub fn try_deposit_resources(
+        &mut self,
+        station: &mut Station,
+        step: usize,
+    ) -> Option<KnowledgeCommit> {
+        let (base_x, base_y) = station.position();
 
-        if self.is_near_base(center_x, center_y) {
-            if self.carried_energy > 0 {
-                station.add_energy(self.carried_energy);
-                self.carried_energy = 0;
-            }
+        if !self.is_near_base(base_x, base_y) || self.is_broken() {
+            return None;
+        }
 
-            if self.carried_minerals > 0 {
-                station.add_minerals(self.carried_minerals);
-                self.carried_minerals = 0;
-            }
+        if self.carried_energy > 0 {
+            station.add_energy(self.carried_energy);
+            self.carried_energy = 0;
+        }
 
-            if self.carried_scientific_data > 0 {
-                station.add_scientific_data(self.carried_scientific_data);
-                self.carried_scientific_data = 0;
-            }
+        if self.carried_minerals > 0 {
+            station.add_minerals(self.carried_minerals);
+            self.carried_minerals = 0;
+        }
+
+        if self.carried_scientific_data.total() > 0 {
+            station.add_scientific_data(&self.carried_scientific_data);
+            self.carried_scientific_data = ScienceTally::default();
+        }
+
+        self.recharge(station);
+
+        let commit = if self.has_data_to_share {
+            self.has_data_to_share = false;
+            Some(station.sync_knowledge(self.id, &self.knowledge, step))
+        } else {
+            None
+        };
+        self.knowledge.clone_from(&station.knowledge);
+
+        commit
+    }
+}
 
-            if self.has_data_to_share {
-                self.has_data_to_share = false;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::MapConfig;
+    use rand::SeedableRng;
+
+    fn robot_on(map: &Map, modules: Vec<RobotModule>) -> Robot {
+        let (x, y) = map.config.centre();
+        Robot::new(
+            0,
+            x,
+            y,
+            modules,
+            KnowledgeMap::new(map.config.width, map.config.height),
+            ChaCha8Rng::seed_from_u64(0),
+        )
+    }
+
+    fn test_map() -> Map {
+        Map::new(MapConfig {
+            width: 50,
+            height: 30,
+            seed: 42,
+        })
+    }
+
+    #[test]
+    fn moving_drains_base_and_module_cost() {
+        let map = test_map();
+        let mut robot = robot_on(&map, vec![RobotModule::Drill]);
+        let (x, y) = (robot.x, robot.y);
+
+        robot.move_towards(x + 1, y, &map, 0);
+
+        assert_eq!((robot.x, robot.y), (x + 1, y));
+        assert_eq!(robot.battery, BATTERY_CAPACITY - robot.move_cost());
+        assert_eq!(robot.move_cost(), 3);
+    }
+
+    #[test]
+    fn flat_robot_is_stranded_until_recharged() {
+        let map = test_map();
+        let mut station = Station::new(0, &map.config, map.config.centre());
+        let mut robot = robot_on(&map, vec![RobotModule::Exploration]);
+        robot.battery = 1;
+        let (x, y) = (robot.x, robot.y);
+
+        robot.move_towards(x + 1, y, &map, 0);
+        assert!(robot.is_stranded());
+        assert_eq!((robot.x, robot.y), (x, y));
+
+        robot.recharge(&mut station);
+        assert!(robot.is_stranded(), "an empty station has nothing to give");
+
+        station.add_energy(5);
+        robot.recharge(&mut station);
+        assert_eq!(robot.battery, BATTERY_CAPACITY);
+        assert_eq!(station.energy_storage, 4);
+
+        robot.battery = BATTERY_CAPACITY - 1;
+        robot.recharge(&mut station);
+        assert_eq!(
+            robot.battery, BATTERY_CAPACITY,
+            "tops up a nearly full battery"
+        );
+        assert_eq!(
+            station.energy_storage, 4,
+            "from what was left of the last unit"
+        );
+
+        robot.battery = BATTERY_CAPACITY - 2;
+        robot.recharge(&mut station);
+        assert_eq!(robot.battery, BATTERY_CAPACITY);
+        assert_eq!(station.energy_storage, 3);
+    }
+
+    #[test]
+    fn drill_fills_its_hold_before_returning() {
+        let mut map = test_map();
+        let mut robot = robot_on(&map, vec![RobotModule::Drill]);
+        assert_eq!(robot.cargo_capacity, 5);
+
+        map.cells[robot.y][robot.x] = CellType::Mineral;
+        map.amounts[robot.y][robot.x] = 8;
+
+        for load in 1..=robot.cargo_capacity {
+            for _ in 1..EXTRACTION_TICKS {
+                assert_eq!(robot.try_gather_resource(&mut map), None, "extracting");
             }
+            assert_eq!(
+                robot.try_gather_resource(&mut map),
+                Some((ResourceType::Mineral, 1))
+            );
+            assert_eq!(robot.carried_minerals, load);
+            assert_eq!(robot.should_return_to_base(), load == 5);
+        }
+
+        assert_eq!(robot.try_gather_resource(&mut map), None, "hold is full");
+        assert_eq!(map.cells[robot.y][robot.x], CellType::Mineral);
+        assert_eq!(map.amounts[robot.y][robot.x], 3);
+    }
+
+    #[test]
+    fn sites_yield_samples_by_kind_until_exhausted() {
+        let mut map = test_map();
+        let mut robot = robot_on(&map, vec![RobotModule::Exploration]);
+        robot.cargo_capacity = 10;
+        let (x, y) = (robot.x, robot.y);
+        map.cells[y][x] = CellType::ScientificSite;
+        map.site_kinds[y][x] = Some(SiteKind::Biosignature);
+        map.amounts[y][x] = SiteKind::Biosignature.yield_budget();
+
+        let mut ticks = 0;
+        while map.cells[y][x] == CellType::ScientificSite {
+            robot.try_gather_resource(&mut map);
+            ticks += 1;
         }
+
+        let budget = SiteKind::Biosignature.yield_budget();
+        assert_eq!(ticks, budget * SiteKind::Biosignature.sampling_ticks());
+        assert_eq!(robot.carried_scientific_data.biosignature, budget);
+        assert_eq!(robot.carried_scientific_data.total(), budget);
+
+        map.cells[y][x] = CellType::ScientificSite;
+        map.site_kinds[y][x] = Some(SiteKind::Geological);
+        map.amounts[y][x] = 1;
+        assert!(!robot.can_work_here(&map), "explorers cannot core samples");
+    }
+
+    #[test]
+    fn combined_modules_sum_capabilities_and_costs() {
+        let map = test_map();
+        let robot = robot_on(&map, vec![RobotModule::Drill, RobotModule::EnergyCollector]);
+
+        assert!(robot.has_capability(Capability::Mine));
+        assert!(robot.has_capability(Capability::HarvestEnergy));
+        assert!(robot.can_sample(SiteKind::Geological));
+        assert!(!robot.has_capability(Capability::Explore));
+        assert_eq!(robot.cargo_capacity, 8);
+        assert_eq!(robot.payload_weight(), 5);
+        // Chassis 1 + drains 2 + 0, plus 2 for the weight above the free 3.
+        assert_eq!(robot.move_cost(), 5);
+    }
+
+    #[test]
+    fn specialist_modules_widen_sensors_and_grade_samples() {
+        let mut map = test_map();
+        let plain = robot_on(&map, vec![RobotModule::Exploration]);
+        let imaging = robot_on(&map, vec![RobotModule::Exploration, RobotModule::Imager]);
+        assert_eq!(imaging.sensor_radius(), plain.sensor_radius() + 2);
+
+        let mut chemist = robot_on(&map, vec![RobotModule::ChemicalAnalyzer]);
+        let (x, y) = (chemist.x, chemist.y);
+        map.cells[y][x] = CellType::ScientificSite;
+        map.site_kinds[y][x] = Some(SiteKind::Chemical);
+        map.amounts[y][x] = 1;
+
+        let mut gathered = None;
+        while gathered.is_none() && map.cells[y][x] == CellType::ScientificSite {
+            gathered = chemist.try_gather_resource(&mut map);
+        }
+        assert_eq!(
+            gathered,
+            Some((ResourceType::ScientificData(SiteKind::Chemical), 2))
+        );
+        assert_eq!(chemist.carried_scientific_data.chemical, 2);
+    }
+
+    #[test]
+    fn state_follows_the_chosen_action() {
+        let mut map = test_map();
+        let mut robot = robot_on(&map, vec![RobotModule::Drill]);
+        let (x, y) = (robot.x, robot.y);
+        let base = (x, y);
+
+        map.cells[y][x] = CellType::Mineral;
+        map.amounts[y][x] = 1;
+        robot.act(Action::Stay, &map, base, 0);
+        assert_eq!(robot.state, RobotState::Harvesting);
+
+        robot.act(Action::MoveTo { x: x + 5, y }, &map, base, 0);
+        assert_eq!(robot.state, RobotState::Exploring);
+        robot.knowledge.observe(x + 5, y, CellType::Empty, 0);
+        robot.act(Action::MoveTo { x: x + 5, y }, &map, base, 0);
+        assert_eq!(robot.state, RobotState::Travelling);
+
+        robot.x = x + 5;
+        robot.battery = 1;
+        robot.act(Action::ReturnToBase, &map, base, 0);
+        assert_eq!(robot.state, RobotState::Stranded);
+
+        let position = (robot.x, robot.y);
+        robot.state = RobotState::Broken;
+        robot.battery = BATTERY_CAPACITY;
+        robot.act(Action::ReturnToBase, &map, base, 0);
+        assert_eq!(robot.state, RobotState::Broken);
+        assert_eq!((robot.x, robot.y), position, "broken robots stay put");
+    }
+
+    #[test]
+    fn robots_only_break_down_when_the_scenario_says_so() {
+        let map = test_map();
+        let mut robot = robot_on(&map, vec![RobotModule::Exploration]);
+        let (x, y) = (robot.x, robot.y);
+        for _ in 0..40 {
+            robot.move_towards(x + 1, y, &map, 0);
+            robot.move_towards(x, y, &map, 0);
+        }
+        assert!(!robot.is_broken(), "breakdowns are off by default");
+
+        robot.battery = BATTERY_CAPACITY;
+        robot.breakdown_chance = 1.0;
+        robot.move_towards(x + 1, y, &map, 0);
+        assert!(robot.is_broken());
+    }
+
+    #[test]
+    fn robots_plan_from_their_own_knowledge_and_learn_from_rock() {
+        let mut map = test_map();
+        let mut robot = robot_on(&map, vec![RobotModule::Exploration]);
+        let (x, y) = (robot.x, robot.y);
+        for row in &mut map.cells[y - 1..=y + 1] {
+            row[x..=x + 2].fill(CellType::Empty);
+        }
+        map.cells[y][x + 1] = CellType::Obstacle;
+
+        // The robot has seen nothing, so it heads straight for the rock,
+        // finds it there and goes round instead.
+        robot.move_towards(x + 2, y, &map, 7);
+        let rock = robot.knowledge.get(x + 1, y).unwrap();
+        assert_eq!((rock.cell, rock.observed_at), (CellType::Obstacle, 7));
+        assert_eq!(robot.x, x);
+        assert_eq!(robot.y.abs_diff(y), 1);
     }
 }
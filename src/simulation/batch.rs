@@ -0,0 +1,164 @@
+use super::recorder::MetricsSnapshot;
+use super::Simulation;
+use crossbeam::channel::unbounded;
+use log::info;
+use std::thread;
+
+/// Runs one simulation per seed for `steps` steps each, spread over
+/// `threads` worker threads, and returns the final snapshot of every run in
+/// the order of `seeds`. Runs whose mission is complete stop early. Each
+/// worker builds its simulations with its own copy of `build`.
+pub fn run_batch<F>(seeds: &[u32], steps: usize, threads: usize, build: F) -> Vec<MetricsSnapshot>
+where
+    F: Fn(u32) -> Simulation + Clone + Send,
+{
+    let (to_workers, queue) = unbounded();
+    for (index, &seed) in seeds.iter().enumerate() {
+        let _ = to_workers.send((index, seed));
+    }
+    drop(to_workers);
+
+    let (to_main, results) = unbounded();
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, seeds.len().max(1)) {
+            let queue = queue.clone();
+            let to_main = to_main.clone();
+            let build = build.clone();
+            scope.spawn(move || {
+                for (index, seed) in queue.iter() {
+                    let mut sim = build(seed);
+                    while sim.stats.simulation_step < steps && !sim.is_mission_complete() {
+                        sim.update();
+                    }
+                    info!(
+                        "Seed {} finished after {} steps",
+                        seed, sim.stats.simulation_step
+                    );
+                    let _ = to_main.send((index, MetricsSnapshot::of(&sim)));
+                }
+            });
+        }
+    });
+    drop(to_main);
+
+    let mut snapshots: Vec<(usize, MetricsSnapshot)> = results.iter().collect();
+    snapshots.sort_by_key(|(index, _)| *index);
+    snapshots
+        .into_iter()
+        .map(|(_, snapshot)| snapshot)
+        .collect()
+}
+
+const COLUMNS: [&str; 10] = [
+    "seed",
+    "robots",
+    "built",
+    "energy",
+    "minerals",
+    "science",
+    "explored%",
+    "distance",
+    "idle",
+    "avg trip",
+];
+
+/// Columns that are not counts, shown with a decimal even for one run.
+const FRACTIONAL: [&str; 2] = ["explored%", "avg trip"];
+
+/// Lays out the final stats of each run as a table, one row per seed, with
+/// the mean of every column underneath.
+pub fn summary_table(snapshots: &[MetricsSnapshot]) -> String {
+    let values: Vec<[f32; 9]> = snapshots
+        .iter()
+        .map(|s| {
+            [
+                s.robots as f32,
+                s.robots_created as f32,
+                s.energy_collected as f32,
+                s.minerals_collected as f32,
+                s.science_collected as f32,
+                s.explored_percent,
+                s.distance_travelled as f32,
+                s.idle_ticks as f32,
+                s.average_trip_length.unwrap_or(0.0),
+            ]
+        })
+        .collect();
+
+    let cells = |label: String, values: &[f32; 9], counts: bool| {
+        let mut row = vec![label];
+        for (column, value) in COLUMNS[1..].iter().zip(values) {
+            if counts && !FRACTIONAL.contains(column) {
+                row.push(format!("{:.0}", value));
+            } else {
+                row.push(format!("{:.1}", value));
+            }
+        }
+        row
+    };
+
+    let mut rows = vec![COLUMNS.map(String::from).to_vec()];
+    for (snapshot, values) in snapshots.iter().zip(&values) {
+        rows.push(cells(snapshot.seed.to_string(), values, true));
+    }
+    if !values.is_empty() {
+        let mut mean = [0.0; 9];
+        for run in &values {
+            for (total, value) in mean.iter_mut().zip(run) {
+                *total += value / values.len() as f32;
+            }
+        }
+        rows.push(cells("mean".to_string(), &mean, false));
+    }
+
+    let widths: Vec<usize> = (0..COLUMNS.len())
+        .map(|c| rows.iter().map(|row| row[c].len()).max().unwrap_or(0))
+        .collect();
+    let mut table = String::new();
+    for row in rows {
+        let padded: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+            .collect();
+        table.push_str(&padded.join("  "));
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::SimulationConfig;
+
+    fn seeded(seed: u32) -> Simulation {
+        Simulation::new(&SimulationConfig {
+            seed,
+            ..SimulationConfig::default()
+        })
+    }
+
+    #[test]
+    fn batch_runs_match_sequential_runs() {
+        let seeds = [3, 1, 2];
+        let snapshots = run_batch(&seeds, 50, 2, seeded);
+
+        for (seed, snapshot) in seeds.iter().zip(&snapshots) {
+            let mut sim = seeded(*seed);
+            for _ in 0..50 {
+                sim.update();
+            }
+            assert_eq!(*snapshot, MetricsSnapshot::of(&sim));
+        }
+
+        let table = summary_table(&snapshots);
+        assert_eq!(table.lines().count(), 1 + seeds.len() + 1);
+        assert!(table
+            .lines()
+            .last()
+            .unwrap()
+            .trim_start()
+            .starts_with("mean"));
+    }
+}
@@ -0,0 +1,210 @@
+use super::SimulationStats;
+use crate::environment::map::CellType;
+use crate::environment::Map;
+use crate::robot::{Robot, RobotState};
+use crate::station::Station;
+
+/// How much of one kind of resource has been found and taken so far.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ResourceMetrics {
+    /// Deposits the map started with.
+    pub deposits: u32,
+    /// Deposits a robot or the station has seen.
+    pub discovered: u32,
+    /// Units all deposits held at the start.
+    pub units: u32,
+    pub harvested: u32,
+    /// Step at which the first deposit was seen.
+    pub first_discovery: Option<usize>,
+}
+
+impl ResourceMetrics {
+    pub fn discovered_percent(&self) -> f32 {
+        percent(self.discovered, self.deposits)
+    }
+
+    pub fn harvested_percent(&self) -> f32 {
+        percent(self.harvested, self.units)
+    }
+}
+
+/// What one robot has been up to since it was built.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RobotMetrics {
+    pub robot_id: usize,
+    /// Cells moved, counting a diagonal step as one.
+    pub distance_travelled: u32,
+    pub idle_ticks: u32,
+    /// Outings from the base that ended back at it.
+    pub trips: u32,
+    /// Ticks spent on those outings.
+    pub trip_ticks: u32,
+    /// Step the robot left the base, while it is away.
+    away_since: Option<usize>,
+}
+
+impl RobotMetrics {
+    pub fn new(robot_id: usize) -> Self {
+        Self {
+            robot_id,
+            ..Self::default()
+        }
+    }
+
+    /// Accounts for one tick of `robot`, which `moved` or not this tick.
+    pub fn record_tick(&mut self, robot: &Robot, moved: bool, base: (usize, usize), step: usize) {
+        if moved {
+            self.distance_travelled += 1;
+        }
+        if robot.state == RobotState::Idle {
+            self.idle_ticks += 1;
+        }
+
+        let at_base = robot.is_near_base(base.0, base.1);
+        match self.away_since {
+            None if !at_base => self.away_since = Some(step),
+            Some(left_at) if at_base => {
+                self.trips += 1;
+                self.trip_ticks += (step - left_at) as u32;
+                self.away_since = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl SimulationStats {
+    pub fn robot_metrics_mut(&mut self, robot_id: usize) -> &mut RobotMetrics {
+        let index = match self.robots.iter().position(|m| m.robot_id == robot_id) {
+            Some(index) => index,
+            None => {
+                self.robots.push(RobotMetrics::new(robot_id));
+                self.robots.len() - 1
+            }
+        };
+        &mut self.robots[index]
+    }
+
+    pub fn distance_travelled(&self) -> u32 {
+        self.robots.iter().map(|m| m.distance_travelled).sum()
+    }
+
+    pub fn idle_ticks(&self) -> u32 {
+        self.robots.iter().map(|m| m.idle_ticks).sum()
+    }
+
+    /// Mean length of every finished trip in the fleet, in ticks.
+    pub fn average_trip_length(&self) -> Option<f32> {
+        let trips: u32 = self.robots.iter().map(|m| m.trips).sum();
+        let ticks: u32 = self.robots.iter().map(|m| m.trip_ticks).sum();
+        (trips > 0).then(|| ticks as f32 / trips as f32)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Deposit {
+    x: usize,
+    y: usize,
+    kind: CellType,
+    units: u32,
+    discovered: bool,
+}
+
+/// Every deposit the map started with, to tell how much of it has been
+/// found and taken once harvesting turns cells back to empty ground.
+#[derive(Debug, Clone)]
+pub struct DepositLedger {
+    deposits: Vec<Deposit>,
+}
+
+impl DepositLedger {
+    pub fn new(map: &Map) -> Self {
+        let mut deposits = Vec::new();
+        for y in 0..map.config.height {
+            for x in 0..map.config.width {
+                let kind = map.cells[y][x];
+                if matches!(
+                    kind,
+                    CellType::Energy | CellType::Mineral | CellType::ScientificSite
+                ) {
+                    deposits.push(Deposit {
+                        x,
+                        y,
+                        kind,
+                        units: map.amounts[y][x],
+                        discovered: false,
+                    });
+                }
+            }
+        }
+        Self { deposits }
+    }
+
+    /// Marks the deposits anyone knows of as discovered and refreshes the
+    /// coverage figures of `stats`. A cell counts as explored once any
+    /// station knows it.
+    pub fn update(
+        &mut self,
+        stats: &mut SimulationStats,
+        map: &Map,
+        stations: &[Station],
+        robots: &[Robot],
+        step: usize,
+    ) {
+        let (width, height) = (map.config.width, map.config.height);
+        let known = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| stations.iter().any(|s| s.knowledge.is_known(x, y)))
+            .count();
+        stats.explored_percent = percent(known as u32, (width * height) as u32);
+
+        let mut energy = ResourceMetrics::default();
+        let mut minerals = ResourceMetrics::default();
+        let mut science = ResourceMetrics::default();
+
+        for deposit in &mut self.deposits {
+            if !deposit.discovered {
+                deposit.discovered = stations
+                    .iter()
+                    .any(|s| s.knowledge.is_known(deposit.x, deposit.y))
+                    || robots
+                        .iter()
+                        .any(|r| r.knowledge.is_known(deposit.x, deposit.y));
+            }
+
+            let metrics = match deposit.kind {
+                CellType::Energy => &mut energy,
+                CellType::Mineral => &mut minerals,
+                _ => &mut science,
+            };
+            metrics.deposits += 1;
+            metrics.units += deposit.units;
+            metrics.harvested += deposit.units - map.amounts[deposit.y][deposit.x];
+            if deposit.discovered {
+                metrics.discovered += 1;
+            }
+        }
+
+        for (metrics, previous) in [
+            (&mut energy, &stats.energy),
+            (&mut minerals, &stats.minerals),
+            (&mut science, &stats.science),
+        ] {
+            metrics.first_discovery = previous
+                .first_discovery
+                .or((metrics.discovered > 0).then_some(step));
+        }
+
+        stats.energy = energy;
+        stats.minerals = minerals;
+        stats.science = science;
+    }
+}
+
+fn percent(part: u32, whole: u32) -> f32 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f32 * 100.0 / whole as f32
+    }
+}
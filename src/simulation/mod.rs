@@ -1,8 +1,32 @@
-use crate::environment::{Map, MapConfig};
-use crate::robot::{Robot, RobotModule};
-use crate::station::Station;
+use crate::environment::map::{ResourceParams, SiteKind, TerrainParams};
+use crate::environment::{KnowledgeCommit, Map, MapConfig};
+use crate::robot::behavior_tree::TreeLibrary;
+use crate::robot::{Capability, Robot, RobotModule, RobotState};
+use crate::station::logistics::{self, ReturnTo};
+use crate::station::tasks::TaskMessage;
+use crate::station::{ScienceTally, Station, StationConfig};
 use crossbeam::channel::{unbounded, Receiver, Sender};
 use log::info;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+pub mod batch;
+pub mod metrics;
+pub mod recorder;
+pub mod scenario;
+
+use metrics::{DepositLedger, ResourceMetrics, RobotMetrics};
+use scenario::{Objectives, Scenario, ScenarioError};
+
+/// Seed of the interactive run, and the first seed of a batch.
+pub const DEFAULT_SEED: u32 = 42;
+
+/// ChaCha stream reserved for simulation decisions; stream 0 is the one
+/// `Map::new` draws from for the same seed.
+const SIMULATION_RNG_STREAM: u64 = 1;
+
+/// How far the station's own sensors reach, in cells.
+const STATION_SENSOR_RADIUS: i32 = 3;
 
 #[derive(Debug, Clone)]
 pub enum SimulationEvent {
@@ -13,173 +37,385 @@ pub enum SimulationEvent {
     RobotCreated {
         id: usize,
     },
+    KnowledgeSynced {
+        commit: KnowledgeCommit,
+    },
+    KnowledgeRelayed {
+        relay_id: usize,
+        robot_id: usize,
+        cells_added: usize,
+    },
+    RobotStateChanged {
+        robot_id: usize,
+        from: RobotState,
+        to: RobotState,
+    },
+    /// A message between station `station_id` and one of its robots.
+    TaskMessage {
+        station_id: usize,
+        message: TaskMessage,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ResourceType {
     Energy,
     Mineral,
-    ScientificData,
+    ScientificData(SiteKind),
 }
 
 pub struct Simulation {
     pub map: Map,
-    pub station: Station,
+    /// Every station, indexed by id. There is always at least one.
+    pub stations: Vec<Station>,
     pub robots: Vec<Robot>,
     event_sender: Option<Sender<SimulationEvent>>,
     event_receiver: Option<Receiver<SimulationEvent>>,
+    rng: ChaCha8Rng,
+    /// Behaviour trees handed to every robot, by role.
+    trees: TreeLibrary,
+    deposits: DepositLedger,
+    objectives: Objectives,
+    return_to: ReturnTo,
 
     pub stats: SimulationStats,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct SimulationStats {
     pub total_energy_collected: u32,
     pub total_minerals_collected: u32,
     pub total_scientific_data_collected: u32,
     pub robots_created: u32,
     pub simulation_step: usize,
+    /// Share of the map the station knows, in percent.
+    pub explored_percent: f32,
+    pub energy: ResourceMetrics,
+    pub minerals: ResourceMetrics,
+    pub science: ResourceMetrics,
+    pub robots: Vec<RobotMetrics>,
+    /// Step at which every objective of the mission was reached.
+    pub objectives_met_at: Option<usize>,
+}
+
+/// What a run starts from. Built from a `Scenario` for a given seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationConfig {
+    pub seed: u32,
+    pub width: usize,
+    pub height: usize,
+    pub terrain: TerrainParams,
+    pub resources: ResourceParams,
+    pub stations: Vec<StationSetup>,
+    /// Economy shared by every station.
+    pub station: StationConfig,
+    pub return_to: ReturnTo,
+    pub objectives: Objectives,
+}
+
+/// A station a run starts with, and the robots based there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationSetup {
+    /// Cell the station stands on, the centre of the map if unset.
+    pub position: Option<(usize, usize)>,
+    /// Module set of each robot the station starts with.
+    pub fleet: Vec<Vec<RobotModule>>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Scenario::default().simulation_config(DEFAULT_SEED)
+    }
 }
 
 impl Simulation {
-    pub fn new() -> Self {
-        let config = MapConfig {
-            width: 50,
-            height: 30,
-            seed: 42,
-        };
-        let map = Map::new(config);
-        let station = Station::new();
+    /// Builds a simulation whose map and robot behaviour are fully determined
+    /// by `config`.
+    pub fn new(config: &SimulationConfig) -> Self {
+        Self::with_behavior_trees(config, TreeLibrary::default())
+    }
 
-        let (sender, receiver) = unbounded();
+    /// Checks `scenario` and builds the mission it describes.
+    pub fn from_scenario(scenario: &Scenario) -> Result<Self, ScenarioError> {
+        scenario.validate()?;
+        Ok(Self::new(&scenario.simulation_config(scenario.seed)))
+    }
+
+    /// Energy held across every station.
+    pub fn stored_energy(&self) -> u32 {
+        self.stations.iter().map(|s| s.energy_storage).sum()
+    }
+
+    /// Minerals held across every station.
+    pub fn stored_minerals(&self) -> u32 {
+        self.stations.iter().map(|s| s.minerals_storage).sum()
+    }
 
-        let mut robots = Vec::new();
-        let center_x = map.config.width / 2;
-        let center_y = map.config.height / 2;
-
-        for i in 0..2 {
-            robots.push(Robot::new(
-                i,
-                center_x,
-                center_y,
-                vec![RobotModule::Exploration],
-            ));
+    /// Samples held across every station.
+    pub fn stored_science(&self) -> ScienceTally {
+        let mut total = ScienceTally::default();
+        for station in &self.stations {
+            total.add_tally(&station.scientific_data_count);
         }
+        total
+    }
+
+    /// Where `robot` unloads and recharges this tick.
+    fn base_of(&self, robot: &Robot) -> (usize, usize) {
+        self.stations[self.return_to.base_for(robot, &self.stations)].position()
+    }
+
+    /// Whether every objective of the mission has been reached.
+    pub fn is_mission_complete(&self) -> bool {
+        self.stats.objectives_met_at.is_some()
+    }
+
+    /// Like `new`, with robots driven by the trees in `trees` instead of the
+    /// bundled ones.
+    pub fn with_behavior_trees(config: &SimulationConfig, trees: TreeLibrary) -> Self {
+        let map_config = MapConfig {
+            width: config.width,
+            height: config.height,
+            seed: config.seed,
+        };
+        let bases: Vec<(usize, usize)> = config
+            .stations
+            .iter()
+            .map(|setup| setup.position.unwrap_or_else(|| map_config.centre()))
+            .collect();
+        let map = Map::generate(map_config, &bases, &config.terrain, &config.resources);
+        let bases = map.bases.clone();
 
-        for i in 2..4 {
-            robots.push(Robot::new(i, center_x, center_y, vec![RobotModule::Drill]));
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed as u64);
+        rng.set_stream(SIMULATION_RNG_STREAM);
+
+        let (sender, receiver) = unbounded();
+
+        let mut stations = Vec::new();
+        let mut robots: Vec<Robot> = Vec::new();
+        for (id, (setup, &base)) in config.stations.iter().zip(&bases).enumerate() {
+            let mut station = Station::new(id, &map.config, base);
+            station.config = config.station.clone();
+            station.build_policy = config.station.build_plan.policy();
+            station.assignment = config.station.allocation.strategy();
+            station.enlist_fleet(&setup.fleet);
+            station.observe(&map, STATION_SENSOR_RADIUS, 0);
+
+            for modules in &setup.fleet {
+                let mut robot = Robot::new(
+                    robots.len(),
+                    base.0,
+                    base.1,
+                    modules.clone(),
+                    station.knowledge.clone(),
+                    robot_rng(&mut rng),
+                );
+                robot.home = id;
+                robot.cargo_capacity = config.station.cargo.robot(&robot.modules);
+                robot.breakdown_chance = config.station.breakdown_chance;
+                robot.behavior = trees.behavior_for(&robot.modules);
+                robots.push(robot);
+            }
+            stations.push(station);
         }
 
-        robots.push(Robot::new(
-            4,
-            center_x,
-            center_y,
-            vec![RobotModule::EnergyCollector],
-        ));
+        let deposits = DepositLedger::new(&map);
 
         Simulation {
             map,
-            station,
+            stations,
             robots,
             event_sender: Some(sender),
             event_receiver: Some(receiver),
+            rng,
+            trees,
+            deposits,
+            objectives: config.objectives.clone(),
+            return_to: config.return_to,
             stats: SimulationStats::default(),
         }
     }
 
     pub fn update(&mut self) {
+        let step = self.stats.simulation_step;
+
+        // `Map::visibility` only drives the display; robots decide from their
+        // own knowledge map.
         self.map.fade_visibility();
 
+        for station in &mut self.stations {
+            for message in station.coordinate_tasks(&mut self.robots, step) {
+                if let Some(ref sender) = self.event_sender {
+                    let _ = sender.send(SimulationEvent::TaskMessage {
+                        station_id: station.id,
+                        message,
+                    });
+                }
+            }
+        }
+        // Robots get the stations' messages before they decide.
+        self.process_events();
+        logistics::dispatch_haulers(&self.stations, &mut self.robots);
+        let claimed: Vec<_> = self
+            .stations
+            .iter()
+            .flat_map(|station| station.tasks.claimed_cells())
+            .collect();
+
         for i in 0..self.robots.len() {
             let robot = &self.robots[i];
-            self.map.update_visibility(robot.x, robot.y, 2);
+            self.map
+                .update_visibility(robot.x, robot.y, robot.sensor_radius());
 
-            let mut specialized_move = false;
-
-            if self.robots[i].should_return_to_base() {
-                let center_x = self.map.config.width / 2;
-                let center_y = self.map.config.height / 2;
-
-                if !self.robots[i].is_near_base(center_x, center_y) {
-                    self.robots[i].move_towards(center_x, center_y, &self.map);
-                    specialized_move = true;
-                }
-            } else {
-                if self.robots[i].modules.contains(&RobotModule::Exploration) {
-                    if let Some((target_x, target_y)) =
-                        find_unexplored_area(self.robots[i].x, self.robots[i].y, &self.map)
-                    {
-                        self.robots[i].move_towards(target_x, target_y, &self.map);
-                        specialized_move = true;
-                    }
-                } else if self.robots[i].modules.contains(&RobotModule::Drill) {
-                    if let Some((target_x, target_y)) = find_nearest_resource(
-                        self.robots[i].x,
-                        self.robots[i].y,
-                        &self.map,
-                        crate::environment::map::CellType::Mineral,
-                    ) {
-                        self.robots[i].move_towards(target_x, target_y, &self.map);
-                        specialized_move = true;
-                    }
-                } else if self.robots[i]
-                    .modules
-                    .contains(&RobotModule::EnergyCollector)
-                {
-                    if let Some((target_x, target_y)) = find_nearest_resource(
-                        self.robots[i].x,
-                        self.robots[i].y,
-                        &self.map,
-                        crate::environment::map::CellType::Energy,
-                    ) {
-                        self.robots[i].move_towards(target_x, target_y, &self.map);
-                        specialized_move = true;
-                    }
+            let base = self.base_of(robot);
+            for bid in self.robots[i].answer_station(base) {
+                if let Some(ref sender) = self.event_sender {
+                    let _ = sender.send(SimulationEvent::TaskMessage {
+                        station_id: self.robots[i].home,
+                        message: TaskMessage::Bid(bid),
+                    });
                 }
             }
+            let action = self.robots[i].decide(&self.map, base, &self.robots, &claimed);
+            let previous_state = self.robots[i].state;
+            let previous_position = (self.robots[i].x, self.robots[i].y);
+            self.robots[i].act(action, &self.map, base, step);
 
-            if !specialized_move {
-                self.robots[i].random_move(&self.map);
+            let robot = &self.robots[i];
+            let moved = (robot.x, robot.y) != previous_position;
+            self.stats
+                .robot_metrics_mut(robot.id)
+                .record_tick(robot, moved, base, step);
+            if robot.state != previous_state {
+                if let Some(ref sender) = self.event_sender {
+                    let _ = sender.send(SimulationEvent::RobotStateChanged {
+                        robot_id: robot.id,
+                        from: previous_state,
+                        to: robot.state,
+                    });
+                }
             }
 
-            if self.robots[i].try_gather_resource(&mut self.map) {
-                let resource_type = if self.robots[i].carried_energy > 0 {
-                    ResourceType::Energy
-                } else if self.robots[i].carried_minerals > 0 {
-                    ResourceType::Mineral
-                } else {
-                    ResourceType::ScientificData
-                };
-
+            if let Some((resource_type, amount)) = self.robots[i].try_gather_resource(&mut self.map)
+            {
                 if let Some(ref sender) = self.event_sender {
                     let _ = sender.send(SimulationEvent::ResourceCollected {
                         resource_type,
-                        amount: 1,
+                        amount,
                     });
                 }
             }
+
+            self.robots[i].observe(&self.map, step);
         }
 
-        self.map
-            .update_visibility(self.map.config.width / 2, self.map.config.height / 2, 3);
+        self.relay_knowledge();
+
+        for station in &mut self.stations {
+            let (x, y) = station.position();
+            self.map.update_visibility(x, y, STATION_SENSOR_RADIUS);
+            station.observe(&self.map, STATION_SENSOR_RADIUS, step);
+        }
 
         for robot in &mut self.robots {
-            robot.try_deposit_resources(&mut self.station, &self.map);
+            let base = self.return_to.base_for(robot, &self.stations);
+            let station = &mut self.stations[base];
+            let commit = robot.try_deposit_resources(station, step);
+            logistics::exchange(robot, &mut self.stations, base);
+            if let Some(commit) = commit {
+                if let Some(ref sender) = self.event_sender {
+                    let _ = sender.send(SimulationEvent::KnowledgeSynced { commit });
+                }
+            }
         }
 
-        if let Some(new_robot) = self.station.try_create_robot() {
+        // Broken robots and those stranded away from a station will never
+        // recharge again, so builds need not keep energy back for them.
+        let mut live = vec![0; self.stations.len()];
+        for robot in &self.robots {
+            let base = self.return_to.base_for(robot, &self.stations);
+            let (x, y) = self.stations[base].position();
+            let lost = robot.is_broken() || (robot.is_stranded() && !robot.is_near_base(x, y));
+            if !lost {
+                live[base] += 1;
+            }
+        }
+        for (station, live) in self.stations.iter_mut().zip(live) {
+            station.set_live_robots(live);
+        }
+
+        for station in &mut self.stations {
+            let Some(mut new_robot) = station.try_create_robot(self.robots.len(), &mut self.rng)
+            else {
+                continue;
+            };
+            new_robot.behavior = self.trees.behavior_for(&new_robot.modules);
             let robot_id = new_robot.id;
             if let Some(ref sender) = self.event_sender {
                 let _ = sender.send(SimulationEvent::RobotCreated { id: robot_id });
             }
             self.robots.push(new_robot);
+            self.stats.robot_metrics_mut(robot_id);
             self.stats.robots_created += 1;
-            info!("Created new robot with ID: {}", robot_id);
+            info!(
+                "Station {} created new robot with ID: {}",
+                station.id, robot_id
+            );
         }
 
+        self.deposits.update(
+            &mut self.stats,
+            &self.map,
+            &self.stations,
+            &self.robots,
+            step,
+        );
         self.process_events();
 
         self.stats.simulation_step += 1;
+
+        if self.stats.objectives_met_at.is_none()
+            && self.objectives.are_met(&self.stats, self.robots.len())
+        {
+            self.stats.objectives_met_at = Some(self.stats.simulation_step);
+            info!(
+                "Mission objectives reached at step {}",
+                self.stats.simulation_step
+            );
+        }
+    }
+
+    /// Every comms relay swaps knowledge with the robots in its range, both
+    /// ways, so discoveries spread through the swarm before anyone gets home.
+    fn relay_knowledge(&mut self) {
+        for r in 0..self.robots.len() {
+            if !self.robots[r].has_capability(Capability::Relay) || self.robots[r].is_broken() {
+                continue;
+            }
+
+            for j in 0..self.robots.len() {
+                if j == r || !self.robots[r].is_within_relay_range(&self.robots[j]) {
+                    continue;
+                }
+
+                let (relay, robot) = pair_mut(&mut self.robots, r, j);
+                let gathered = relay.knowledge.merge(&robot.knowledge);
+                let shared = robot.knowledge.merge(&relay.knowledge);
+                if gathered.added > 0 {
+                    relay.has_data_to_share = true;
+                }
+                if shared.added > 0 {
+                    robot.has_data_to_share = true;
+                    if let Some(ref sender) = self.event_sender {
+                        let _ = sender.send(SimulationEvent::KnowledgeRelayed {
+                            relay_id: relay.id,
+                            robot_id: robot.id,
+                            cells_added: shared.added,
+                        });
+                    }
+                }
+            }
+        }
     }
 
     fn process_events(&mut self) {
@@ -196,95 +432,366 @@ impl Simulation {
                         ResourceType::Mineral => {
                             self.stats.total_minerals_collected += amount;
                         }
-                        ResourceType::ScientificData => {
+                        ResourceType::ScientificData(_) => {
                             self.stats.total_scientific_data_collected += amount;
                         }
                     },
                     SimulationEvent::RobotCreated { id } => {
                         info!("Processed robot creation event for robot ID: {}", id);
                     }
+                    SimulationEvent::KnowledgeRelayed {
+                        relay_id,
+                        robot_id,
+                        cells_added,
+                    } => {
+                        info!(
+                            "Relay {} passed {} cells to robot {}",
+                            relay_id, cells_added, robot_id
+                        );
+                    }
+                    SimulationEvent::RobotStateChanged { robot_id, from, to } => {
+                        info!(
+                            "Robot {} went from {} to {}",
+                            robot_id,
+                            from.label(),
+                            to.label()
+                        );
+                    }
+                    SimulationEvent::TaskMessage {
+                        station_id,
+                        message,
+                    } => match message {
+                        TaskMessage::Bid(bid) => {
+                            self.stations[station_id].assignment.receive_bid(bid);
+                        }
+                        TaskMessage::CallForBids { robot_id, .. }
+                        | TaskMessage::Award { robot_id, .. } => {
+                            if let TaskMessage::Award { task, .. } = message {
+                                info!(
+                                    "Station {} awarded task {} to robot {}",
+                                    station_id, task.id, robot_id
+                                );
+                            }
+                            if let Some(robot) = self.robots.iter_mut().find(|r| r.id == robot_id) {
+                                robot.receive(message);
+                            }
+                        }
+                    },
+                    SimulationEvent::KnowledgeSynced { commit } => {
+                        info!(
+                            "Knowledge commit #{} from robot {}: +{} ~{} !{}",
+                            commit.id,
+                            commit.robot_id,
+                            commit.outcome.added,
+                            commit.outcome.updated,
+                            commit.outcome.conflicts.len()
+                        );
+                    }
                 }
             }
         }
     }
 }
 
-fn find_unexplored_area(robot_x: usize, robot_y: usize, map: &Map) -> Option<(usize, usize)> {
-    use crate::environment::map::CellVisibility;
-
-    let mut radius = 3;
-    let max_radius = 10;
-
-    while radius <= max_radius {
-        for dx in -radius..=radius {
-            for dy in -radius..=radius {
-                if dx * dx + dy * dy >= (radius - 2) * (radius - 2)
-                    && dx * dx + dy * dy <= radius * radius
-                {
-                    let x =
-                        (robot_x as isize + dx).clamp(0, map.config.width as isize - 1) as usize;
-                    let y =
-                        (robot_y as isize + dy).clamp(0, map.config.height as isize - 1) as usize;
-
-                    if map.visibility[y][x] == CellVisibility::Hidden && map.is_walkable(x, y) {
-                        return Some((x, y));
-                    }
-                }
+/// Derives an independent RNG sub-stream for a new robot.
+fn robot_rng(rng: &mut impl Rng) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(rng.gen())
+}
+
+/// Mutable references to two distinct elements of `items`.
+fn pair_mut<T>(items: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    assert_ne!(a, b);
+    if a < b {
+        let (left, right) = items.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = items.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::robot::CargoCapacities;
+    use crate::station::tasks::Allocation;
+
+    fn seeded(seed: u32) -> Simulation {
+        Simulation::new(&SimulationConfig {
+            seed,
+            ..SimulationConfig::default()
+        })
+    }
+
+    fn run(seed: u32, steps: usize) -> (Vec<(usize, usize)>, SimulationStats) {
+        let mut sim = seeded(seed);
+        for _ in 0..steps {
+            sim.update();
+        }
+        let positions = sim.robots.iter().map(|r| (r.x, r.y)).collect();
+        (positions, sim.stats)
+    }
+
+    #[test]
+    fn same_seed_produces_identical_runs() {
+        assert_eq!(run(7, 300), run(7, 300));
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        assert_ne!(run(7, 300).0, run(8, 300).0);
+    }
+
+    #[test]
+    fn metrics_follow_the_fleet_and_the_deposits() {
+        let mut sim = seeded(42);
+        for _ in 0..300 {
+            sim.update();
+        }
+        let stats = &sim.stats;
+
+        assert!(stats.explored_percent > 0.0 && stats.explored_percent <= 100.0);
+        assert_eq!(stats.robots.len(), sim.robots.len());
+        assert!(stats.distance_travelled() > 0);
+
+        for metrics in [&stats.energy, &stats.minerals, &stats.science] {
+            assert!(metrics.discovered <= metrics.deposits);
+            assert!(metrics.harvested <= metrics.units);
+            if metrics.harvested > 0 {
+                assert!(metrics.first_discovery.is_some());
             }
         }
+    }
+
+    #[test]
+    fn frontier_exploration_maps_more_than_wandering() {
+        // Explorers alone, with energy to spare, so coverage only depends on
+        // where they choose to go.
+        let coverage = |explorer: serde_json::Value, seed: u32| {
+            let mut trees: serde_json::Value =
+                serde_json::from_str(include_str!("../../assets/behavior_trees.json")).unwrap();
+            trees["explorer"] = explorer;
+            let trees = TreeLibrary::from_json(&trees.to_string()).unwrap();
+
+            let mut config = SimulationConfig {
+                seed,
+                ..SimulationConfig::default()
+            };
+            config.stations[0].fleet = vec![vec![RobotModule::Exploration]; 3];
+            let mut sim = Simulation::with_behavior_trees(&config, trees);
+            sim.stations[0].energy_storage = 10_000;
+            for _ in 0..300 {
+                sim.update();
+            }
+            sim.stats.explored_percent
+        };
+        let explorer = |fallback: &str| {
+            serde_json::json!({"node": "selector", "children": [
+                {"node": "sequence", "children": [
+                    {"node": "low_battery"}, {"node": "return_to_base"}, {"node": "wait"}
+                ]},
+                {"node": fallback},
+                {"node": "wander"}
+            ]})
+        };
 
-        radius += 3;
+        for seed in 40..44 {
+            let frontier = coverage(explorer("explore"), seed);
+            let wandering = coverage(explorer("wander"), seed);
+            assert!(
+                frontier > wandering,
+                "seed {seed}: frontiers {frontier:.1}% vs wandering {wandering:.1}%"
+            );
+        }
     }
 
-    radius = 3;
-    while radius <= max_radius {
-        for dx in -radius..=radius {
-            for dy in -radius..=radius {
-                if dx * dx + dy * dy >= (radius - 2) * (radius - 2)
-                    && dx * dx + dy * dy <= radius * radius
-                {
-                    let x =
-                        (robot_x as isize + dx).clamp(0, map.config.width as isize - 1) as usize;
-                    let y =
-                        (robot_y as isize + dy).clamp(0, map.config.height as isize - 1) as usize;
-
-                    if map.visibility[y][x] == CellVisibility::Explored && map.is_walkable(x, y) {
-                        return Some((x, y));
-                    }
+    #[test]
+    fn contract_net_awards_go_through_the_robots() {
+        let mut scenario = Scenario::default();
+        scenario.station.allocation = Allocation::ContractNet;
+        let mut sim = Simulation::from_scenario(&scenario).unwrap();
+        let mut awarded = false;
+        for _ in 0..100 {
+            sim.update();
+            for robot in &sim.robots {
+                if let Some(task) = robot.task {
+                    awarded = true;
+                    assert!(sim.stations[robot.home]
+                        .tasks
+                        .is_claimed_by(task.id, robot.id));
                 }
             }
         }
+        assert!(awarded);
+    }
+
+    #[test]
+    fn scenarios_end_once_their_objectives_are_met() {
+        let mut scenario = Scenario::default();
+        scenario.objectives.explored_percent = Some(5.0);
+        let mut sim = Simulation::from_scenario(&scenario).unwrap();
+        while !sim.is_mission_complete() && sim.stats.simulation_step < scenario.steps {
+            sim.update();
+        }
+        let met_at = sim.stats.objectives_met_at.expect("objective reached");
+        assert!(sim.stats.explored_percent >= 5.0);
+        assert_eq!(met_at, sim.stats.simulation_step);
 
-        radius += 3;
+        scenario.map.width = 5;
+        assert!(Simulation::from_scenario(&scenario).is_err());
     }
 
-    None
-}
+    #[test]
+    fn robots_work_from_an_off_centre_station() {
+        let base = (8, 5);
+        let mut scenario = Scenario::default();
+        scenario.map.width = 60;
+        scenario.map.height = 20;
+        scenario.map.stations = vec![base];
+        let mut sim = Simulation::from_scenario(&scenario).unwrap();
+        assert_eq!(sim.stations[0].position(), base);
+        assert!(sim.robots.iter().all(|r| (r.x, r.y) == base));
+        assert!(sim.map.is_walkable(base.0 + 1, base.1 - 1));
+
+        // Builds and recharges spend the stocks again, so watch them rise.
+        let initial = (
+            sim.stations[0].energy_storage,
+            sim.stations[0].minerals_storage,
+        );
+        let delivered = (0..400).any(|_| {
+            sim.update();
+            sim.stations[0].energy_storage > initial.0
+                || sim.stations[0].minerals_storage > initial.1
+        });
+        assert!(delivered, "nothing made it back to the station");
+    }
 
-fn find_nearest_resource(
-    robot_x: usize,
-    robot_y: usize,
-    map: &Map,
-    resource_type: crate::environment::map::CellType,
-) -> Option<(usize, usize)> {
-    use crate::environment::map::CellVisibility;
-
-    let mut closest_dist = f32::MAX;
-    let mut closest_point = None;
-
-    for y in 0..map.config.height {
-        for x in 0..map.config.width {
-            if map.visibility[y][x] != CellVisibility::Hidden && map.cells[y][x] == resource_type {
-                let dist = ((x as isize - robot_x as isize).pow(2)
-                    + (y as isize - robot_y as isize).pow(2)) as f32;
-
-                if dist < closest_dist {
-                    closest_dist = dist;
-                    closest_point = Some((x, y));
-                }
+    #[test]
+    fn haulers_stock_stations_that_gather_nothing() {
+        let scenario = Scenario::from_toml(
+            r#"
+            [map]
+            width = 80
+            height = 30
+            stations = [[15, 15], [65, 15]]
+
+            [map.resources.energy]
+            density = 0.03
+
+            [[robots]]
+            modules = ["drill"]
+            count = 2
+
+            [[robots]]
+            modules = ["energy-collector"]
+            count = 2
+
+            [[robots]]
+            modules = ["cargo-bay"]
+            station = 1
+            "#,
+        )
+        .unwrap();
+        let mut sim = Simulation::from_scenario(&scenario).unwrap();
+        assert_eq!(sim.stations.len(), 2);
+        assert_eq!(sim.robots[4].home, 1);
+        assert_eq!((sim.robots[4].x, sim.robots[4].y), (65, 15));
+
+        let hauled = (0..1500).any(|_| {
+            sim.update();
+            sim.robots[4].haul.is_some_and(|haul| haul.loaded)
+        });
+        assert!(hauled, "station 1 has no minerals of its own");
+    }
+
+    #[test]
+    fn bundled_example_hauls_stock_and_builds_robots() {
+        let scenario =
+            Scenario::from_toml(include_str!("../../assets/scenarios/example.toml")).unwrap();
+        let mut sim = Simulation::from_scenario(&scenario).unwrap();
+
+        let mut hauled = false;
+        while !(hauled && sim.stats.robots_created > 0)
+            && sim.stats.simulation_step < scenario.steps
+        {
+            sim.update();
+            hauled |= sim.robots.iter().any(|r| r.haul.is_some_and(|h| h.loaded));
+        }
+        assert!(hauled, "the hauler never left with a load");
+        assert!(sim.stats.robots_created > 0, "no robot was built");
+    }
+
+    #[test]
+    fn default_missions_harvest_enough_energy_to_build() {
+        for seed in [42, 44] {
+            let config = SimulationConfig {
+                seed,
+                ..SimulationConfig::default()
+            };
+            let mut sim = Simulation::new(&config);
+            while sim.stats.robots_created == 0 && sim.stats.simulation_step < 300 {
+                sim.update();
             }
+            assert!(sim.stats.robots_created > 0, "seed {seed}: nothing built");
         }
     }
 
-    closest_point
+    #[test]
+    fn lost_robots_free_their_recharge_reserve() {
+        let mut sim = Simulation::new(&SimulationConfig::default());
+        sim.update();
+        let station = &sim.stations[0];
+        assert_eq!(
+            station.recharge_reserve() as usize - station.build_queue().len(),
+            5
+        );
+
+        sim.robots[0].state = RobotState::Broken;
+        sim.update();
+        let station = &sim.stations[0];
+        assert_eq!(
+            station.recharge_reserve() as usize - station.build_queue().len(),
+            4
+        );
+    }
+
+    #[test]
+    fn scenarios_size_the_hold_of_every_module() {
+        let scenario = Scenario::from_toml(
+            "[station.cargo]\nexploration = 4\ndrill = 8\nenergy_collector = 6\ncomms_relay = 2",
+        )
+        .unwrap();
+        assert_eq!(
+            scenario.station.cargo.cargo_bay, 10,
+            "left at its stock size"
+        );
+
+        let mut sim = Simulation::from_scenario(&scenario).unwrap();
+        for robot in &sim.robots {
+            assert_eq!(
+                robot.cargo_capacity,
+                scenario.station.cargo.robot(&robot.modules)
+            );
+        }
+        assert!(sim.robots.iter().any(|r| r.cargo_capacity == 8));
+
+        let station = &mut sim.stations[0];
+        station.add_energy(100);
+        station.add_minerals(100);
+        let mut samples = ScienceTally::default();
+        samples.add(SiteKind::Geological, 100);
+        station.add_scientific_data(&samples);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let built = (0..100)
+            .find_map(|_| station.try_create_robot(99, &mut rng))
+            .unwrap();
+        assert_eq!(
+            built.cargo_capacity,
+            scenario.station.cargo.robot(&built.modules)
+        );
+        assert_ne!(
+            built.cargo_capacity,
+            CargoCapacities::default().robot(&built.modules)
+        );
+    }
 }
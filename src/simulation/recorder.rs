@@ -0,0 +1,243 @@
+use super::Simulation;
+use crate::robot::{RobotModule, RobotState};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// How snapshots are laid out on disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricsFormat {
+    /// One header line, then one comma-separated row per snapshot.
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+impl MetricsFormat {
+    /// Guesses the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for MetricsFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "csv" => Ok(MetricsFormat::Csv),
+            "jsonl" => Ok(MetricsFormat::JsonLines),
+            _ => Err(format!(
+                "unknown metrics format \"{}\", expected \"csv\" or \"jsonl\"",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MetricsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricsFormat::Csv => write!(f, "csv"),
+            MetricsFormat::JsonLines => write!(f, "jsonl"),
+        }
+    }
+}
+
+/// The state of a run at one step, flattened so each field is a CSV column.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    pub seed: u32,
+    pub step: usize,
+    pub robots: usize,
+    pub explorers: usize,
+    pub miners: usize,
+    pub energy_collectors: usize,
+    pub relays: usize,
+    pub haulers: usize,
+    pub idle_robots: usize,
+    pub broken_robots: usize,
+    pub stranded_robots: usize,
+    /// Stocks summed over every station.
+    pub station_energy: u32,
+    pub station_minerals: u32,
+    pub station_geological: u32,
+    pub station_chemical: u32,
+    pub station_biosignature: u32,
+    pub energy_collected: u32,
+    pub minerals_collected: u32,
+    pub science_collected: u32,
+    pub robots_created: u32,
+    pub explored_percent: f32,
+    pub energy_discovered_percent: f32,
+    pub energy_harvested_percent: f32,
+    pub minerals_discovered_percent: f32,
+    pub minerals_harvested_percent: f32,
+    pub science_discovered_percent: f32,
+    pub science_harvested_percent: f32,
+    pub distance_travelled: u32,
+    pub idle_ticks: u32,
+    pub average_trip_length: Option<f32>,
+}
+
+impl MetricsSnapshot {
+    pub fn of(sim: &Simulation) -> Self {
+        let stats = &sim.stats;
+        let with_module = |module| {
+            sim.robots
+                .iter()
+                .filter(|r| r.modules.contains(&module))
+                .count()
+        };
+        let in_state = |state| sim.robots.iter().filter(|r| r.state == state).count();
+        let science = sim.stored_science();
+
+        Self {
+            seed: sim.map.config.seed,
+            step: stats.simulation_step,
+            robots: sim.robots.len(),
+            explorers: with_module(RobotModule::Exploration),
+            miners: with_module(RobotModule::Drill),
+            energy_collectors: with_module(RobotModule::EnergyCollector),
+            relays: with_module(RobotModule::CommsRelay),
+            haulers: with_module(RobotModule::CargoBay),
+            idle_robots: in_state(RobotState::Idle),
+            broken_robots: in_state(RobotState::Broken),
+            stranded_robots: in_state(RobotState::Stranded),
+            station_energy: sim.stored_energy(),
+            station_minerals: sim.stored_minerals(),
+            station_geological: science.geological,
+            station_chemical: science.chemical,
+            station_biosignature: science.biosignature,
+            energy_collected: stats.total_energy_collected,
+            minerals_collected: stats.total_minerals_collected,
+            science_collected: stats.total_scientific_data_collected,
+            robots_created: stats.robots_created,
+            explored_percent: stats.explored_percent,
+            energy_discovered_percent: stats.energy.discovered_percent(),
+            energy_harvested_percent: stats.energy.harvested_percent(),
+            minerals_discovered_percent: stats.minerals.discovered_percent(),
+            minerals_harvested_percent: stats.minerals.harvested_percent(),
+            science_discovered_percent: stats.science.discovered_percent(),
+            science_harvested_percent: stats.science.harvested_percent(),
+            distance_travelled: stats.distance_travelled(),
+            idle_ticks: stats.idle_ticks(),
+            average_trip_length: stats.average_trip_length(),
+        }
+    }
+}
+
+/// Writes a snapshot of the simulation every `interval` steps.
+pub struct MetricsRecorder<W: Write = BufWriter<File>> {
+    writer: W,
+    format: MetricsFormat,
+    interval: usize,
+    wrote_header: bool,
+}
+
+impl MetricsRecorder {
+    /// Records into a new file at `path`, replacing any file already there.
+    pub fn create(path: &Path, format: MetricsFormat, interval: usize) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::new(BufWriter::new(file), format, interval))
+    }
+}
+
+impl<W: Write> MetricsRecorder<W> {
+    pub fn new(writer: W, format: MetricsFormat, interval: usize) -> Self {
+        Self {
+            writer,
+            format,
+            interval: interval.max(1),
+            wrote_header: false,
+        }
+    }
+
+    /// Writes a snapshot of `sim` if its step falls on the interval. Each
+    /// snapshot is flushed, so an interrupted run keeps what it recorded.
+    pub fn record(&mut self, sim: &Simulation) -> io::Result<()> {
+        if sim.stats.simulation_step.is_multiple_of(self.interval) {
+            self.write(&MetricsSnapshot::of(sim))?;
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, snapshot: &MetricsSnapshot) -> io::Result<()> {
+        match self.format {
+            MetricsFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, snapshot)?;
+                writeln!(self.writer)
+            }
+            MetricsFormat::Csv => {
+                let Value::Object(fields) = serde_json::to_value(snapshot)? else {
+                    unreachable!("snapshots serialize to objects");
+                };
+                if !self.wrote_header {
+                    let header: Vec<&str> = fields.keys().map(String::as_str).collect();
+                    writeln!(self.writer, "{}", header.join(","))?;
+                    self.wrote_header = true;
+                }
+                let row: Vec<String> = fields.values().map(csv_field).collect();
+                writeln!(self.writer, "{}", row.join(","))
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        // Snapshot fractions are f32; widening them to f64 would print noise.
+        Value::Number(number) if number.is_f64() => {
+            (number.as_f64().unwrap_or_default() as f32).to_string()
+        }
+        Value::String(text) => format!("\"{}\"", text.replace('"', "\"\"")),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::SimulationConfig;
+
+    #[test]
+    fn snapshots_are_written_every_interval() {
+        let mut sim = Simulation::new(&SimulationConfig::default());
+        let (mut csv, mut jsonl) = (Vec::new(), Vec::new());
+        let mut csv_recorder = MetricsRecorder::new(&mut csv, MetricsFormat::Csv, 10);
+        let mut jsonl_recorder = MetricsRecorder::new(&mut jsonl, MetricsFormat::JsonLines, 10);
+        for _ in 0..25 {
+            sim.update();
+            csv_recorder.record(&sim).unwrap();
+            jsonl_recorder.record(&sim).unwrap();
+        }
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("seed,step,robots,"));
+        assert!(lines[2].starts_with("42,20,"));
+        assert_eq!(lines[1].split(',').count(), lines[0].split(',').count());
+
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        let steps: Vec<u64> = jsonl
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<Value>(line).unwrap()["step"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(steps, vec![10, 20]);
+    }
+}
@@ -0,0 +1,484 @@
+use super::{SimulationConfig, SimulationStats, StationSetup, DEFAULT_SEED};
+use crate::environment::map::{Abundance, ResourceParams, TerrainParams};
+use crate::robot::{check_loadout, RobotModule};
+use crate::station::build::BuildPlan;
+use crate::station::logistics::ReturnTo;
+use crate::station::StationConfig;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+/// Smallest map side accepted, in cells. The station and its sensors need
+/// room around the centre.
+pub const MIN_MAP_SIDE: usize = 10;
+/// Largest map side accepted, in cells.
+pub const MAX_MAP_SIDE: usize = 500;
+
+/// A mission: the planet, the robots sent there, the station's economy and
+/// what counts as success. Loaded from TOML or JSON; every field has a
+/// default, so a file only lists what it changes.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    pub seed: u32,
+    /// Steps the mission lasts at most.
+    pub steps: usize,
+    pub map: MapSection,
+    pub robots: Vec<RobotGroup>,
+    /// Economy shared by every station.
+    pub station: StationConfig,
+    /// Station robots head back to: "home" or "nearest".
+    pub return_to: ReturnTo,
+    pub objectives: Objectives,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MapSection {
+    pub width: usize,
+    pub height: usize,
+    /// Cells the stations stand on, as `[x, y]` pairs. Left empty, a single
+    /// station stands in the centre. A station that would be walled in is
+    /// moved to the nearest open ground.
+    pub stations: Vec<(usize, usize)>,
+    pub terrain: TerrainParams,
+    pub resources: ResourceParams,
+}
+
+impl Default for MapSection {
+    fn default() -> Self {
+        Self {
+            width: 50,
+            height: 30,
+            stations: Vec::new(),
+            terrain: TerrainParams::default(),
+            resources: ResourceParams::default(),
+        }
+    }
+}
+
+/// `count` robots fitted with `modules`, starting from station `station`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RobotGroup {
+    pub modules: Vec<RobotModule>,
+    #[serde(default = "RobotGroup::default_count")]
+    pub count: usize,
+    /// Index into `map.stations`.
+    #[serde(default)]
+    pub station: usize,
+}
+
+impl RobotGroup {
+    fn default_count() -> usize {
+        1
+    }
+}
+
+/// Targets that end the mission once all of those set are reached.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Objectives {
+    pub energy: Option<u32>,
+    pub minerals: Option<u32>,
+    pub science: Option<u32>,
+    pub explored_percent: Option<f32>,
+    pub robots: Option<usize>,
+}
+
+impl Objectives {
+    pub fn is_empty(&self) -> bool {
+        *self == Objectives::default()
+    }
+
+    /// Whether `stats`, with `robots` robots out, reach every target set.
+    /// Never true when no target is set.
+    pub fn are_met(&self, stats: &SimulationStats, robots: usize) -> bool {
+        let reached = |target: Option<u32>, value: u32| target.is_none_or(|t| value >= t);
+        !self.is_empty()
+            && reached(self.energy, stats.total_energy_collected)
+            && reached(self.minerals, stats.total_minerals_collected)
+            && reached(self.science, stats.total_scientific_data_collected)
+            && self
+                .explored_percent
+                .is_none_or(|t| stats.explored_percent >= t)
+            && self.robots.is_none_or(|t| robots >= t)
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        let group = |module, count| RobotGroup {
+            modules: vec![module],
+            count,
+            station: 0,
+        };
+        Self {
+            name: "default".to_string(),
+            seed: DEFAULT_SEED,
+            steps: 1000,
+            map: MapSection::default(),
+            robots: vec![
+                group(RobotModule::Exploration, 2),
+                group(RobotModule::Drill, 2),
+                group(RobotModule::EnergyCollector, 1),
+            ],
+            station: StationConfig::default(),
+            return_to: ReturnTo::default(),
+            objectives: Objectives::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    /// The file is not valid TOML or JSON, or does not match the format.
+    Parse(String),
+    /// A field holds a value the simulation cannot run with.
+    Invalid {
+        field: String,
+        message: String,
+    },
+}
+
+impl ScenarioError {
+    fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ScenarioError::Invalid {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(err) => write!(f, "cannot read scenario: {}", err),
+            ScenarioError::Parse(err) => write!(f, "invalid scenario: {}", err),
+            ScenarioError::Invalid { field, message } => {
+                write!(f, "invalid scenario field `{}`: {}", field, message)
+            }
+        }
+    }
+}
+
+impl Scenario {
+    /// Reads a scenario from `path`, as JSON if it ends in ".json" and as
+    /// TOML otherwise. Call `validate` before running it.
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let text = std::fs::read_to_string(path).map_err(ScenarioError::Io)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ScenarioError> {
+        toml::from_str(text).map_err(|err| ScenarioError::Parse(err.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ScenarioError> {
+        serde_json::from_str(text).map_err(|err| ScenarioError::Parse(err.to_string()))
+    }
+
+    /// Checks every field the types alone cannot, naming the first bad one.
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        if self.steps == 0 {
+            return Err(ScenarioError::invalid("steps", "must be at least 1"));
+        }
+
+        for (field, side) in [
+            ("map.width", self.map.width),
+            ("map.height", self.map.height),
+        ] {
+            if !(MIN_MAP_SIDE..=MAX_MAP_SIDE).contains(&side) {
+                return Err(ScenarioError::invalid(
+                    field,
+                    format!(
+                        "maps are between {} and {} cells a side, got {}",
+                        MIN_MAP_SIDE, MAX_MAP_SIDE, side
+                    ),
+                ));
+            }
+        }
+
+        for (i, &(x, y)) in self.map.stations.iter().enumerate() {
+            let field = format!("map.stations[{}]", i);
+            // Each station needs the ring of cells around it cleared.
+            if !(1..self.map.width - 1).contains(&x) || !(1..self.map.height - 1).contains(&y) {
+                return Err(ScenarioError::invalid(
+                    field,
+                    format!(
+                        "({}, {}) is not at least one cell inside the {}x{} map",
+                        x, y, self.map.width, self.map.height
+                    ),
+                ));
+            }
+            if self.map.stations[..i]
+                .iter()
+                .any(|&(ox, oy)| x.abs_diff(ox) <= 2 && y.abs_diff(oy) <= 2)
+            {
+                return Err(ScenarioError::invalid(
+                    field,
+                    "stations must stand at least three cells apart",
+                ));
+            }
+        }
+
+        let terrain = &self.map.terrain;
+        for (field, scale) in [
+            ("map.terrain.scale", terrain.scale),
+            ("map.terrain.richness_scale", terrain.richness_scale),
+        ] {
+            if scale.is_nan() || scale <= 0.0 {
+                return Err(ScenarioError::invalid(field, "must be above 0"));
+            }
+        }
+        if !(-1.0..=1.0).contains(&terrain.obstacle_threshold) {
+            return Err(ScenarioError::invalid(
+                "map.terrain.obstacle_threshold",
+                "must be between -1 and 1",
+            ));
+        }
+
+        let resources = &self.map.resources;
+        let cells = self.map.width * self.map.height;
+        let mut deposits = 0;
+        for (field, abundance) in [
+            ("map.resources.energy", &resources.energy),
+            ("map.resources.minerals", &resources.minerals),
+            ("map.resources.science_sites", &resources.science_sites),
+        ] {
+            check_abundance(field, abundance)?;
+            deposits += abundance.resolve(cells);
+        }
+        if deposits > cells / 2 {
+            return Err(ScenarioError::invalid(
+                "map.resources",
+                format!(
+                    "{} deposits would cover more than half of the {} cells",
+                    deposits, cells
+                ),
+            ));
+        }
+
+        if self.robots.is_empty() {
+            return Err(ScenarioError::invalid(
+                "robots",
+                "the mission needs at least one robot",
+            ));
+        }
+        for (i, group) in self.robots.iter().enumerate() {
+            check_loadout(&group.modules).map_err(|message| {
+                ScenarioError::invalid(format!("robots[{}].modules", i), message)
+            })?;
+            if group.count == 0 {
+                return Err(ScenarioError::invalid(
+                    format!("robots[{}].count", i),
+                    "must be at least 1",
+                ));
+            }
+            let stations = self.map.stations.len().max(1);
+            if group.station >= stations {
+                return Err(ScenarioError::invalid(
+                    format!("robots[{}].station", i),
+                    format!("there are only {} stations", stations),
+                ));
+            }
+        }
+
+        let station = &self.station;
+        for module in RobotModule::ALL {
+            if station.costs.of(&module).units() == 0 {
+                return Err(ScenarioError::invalid(
+                    format!("station.costs.{}", module.name().replace('-', "_")),
+                    "a module must cost at least one unit of stock",
+                ));
+            }
+        }
+        if let BuildPlan::Scripted { schedule } = &station.build_plan {
+            if schedule.is_empty() {
+                return Err(ScenarioError::invalid(
+                    "station.build_plan.scripted.schedule",
+                    "must list at least one robot",
+                ));
+            }
+            for (i, modules) in schedule.iter().enumerate() {
+                check_loadout(modules).map_err(|message| {
+                    ScenarioError::invalid(
+                        format!("station.build_plan.scripted.schedule[{}]", i),
+                        message,
+                    )
+                })?;
+            }
+        }
+        if station.queue_length == 0 {
+            return Err(ScenarioError::invalid(
+                "station.queue_length",
+                "must be at least 1",
+            ));
+        }
+        if station.relay_fleet_share == 0 {
+            return Err(ScenarioError::invalid(
+                "station.relay_fleet_share",
+                "must be at least 1",
+            ));
+        }
+        if !(0.0..=1.0).contains(&station.breakdown_chance) {
+            return Err(ScenarioError::invalid(
+                "station.breakdown_chance",
+                "must be between 0 and 1",
+            ));
+        }
+        let mix = station.target_mix;
+        let shares = [mix.exploration, mix.drill, mix.energy_collector];
+        if shares.iter().any(|share| !(0.0..=1.0).contains(share))
+            || (shares.iter().sum::<f32>() - 1.0).abs() > 0.01
+        {
+            return Err(ScenarioError::invalid(
+                "station.target_mix",
+                "shares must each be between 0 and 1 and add up to 1",
+            ));
+        }
+
+        if self
+            .objectives
+            .explored_percent
+            .is_some_and(|target| !(0.0..=100.0).contains(&target))
+        {
+            return Err(ScenarioError::invalid(
+                "objectives.explored_percent",
+                "must be between 0 and 100",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Module set of every robot starting from station `station`, one entry
+    /// per robot.
+    pub fn fleet(&self, station: usize) -> Vec<Vec<RobotModule>> {
+        self.robots
+            .iter()
+            .filter(|group| group.station == station)
+            .flat_map(|group| std::iter::repeat_n(group.modules.clone(), group.count))
+            .collect()
+    }
+
+    /// Starting point of the mission, run on `seed` rather than its own.
+    pub fn simulation_config(&self, seed: u32) -> SimulationConfig {
+        let positions: Vec<Option<(usize, usize)>> = if self.map.stations.is_empty() {
+            vec![None]
+        } else {
+            self.map.stations.iter().copied().map(Some).collect()
+        };
+        let stations = positions
+            .into_iter()
+            .enumerate()
+            .map(|(id, position)| StationSetup {
+                position,
+                fleet: self.fleet(id),
+            })
+            .collect();
+
+        SimulationConfig {
+            seed,
+            width: self.map.width,
+            height: self.map.height,
+            terrain: self.map.terrain.clone(),
+            resources: self.map.resources.clone(),
+            stations,
+            station: self.station.clone(),
+            return_to: self.return_to,
+            objectives: self.objectives.clone(),
+        }
+    }
+}
+
+fn check_abundance(field: &str, abundance: &Abundance) -> Result<(), ScenarioError> {
+    if abundance.count.is_some() && abundance.density.is_some() {
+        return Err(ScenarioError::invalid(
+            field,
+            "give a count or a density, not both",
+        ));
+    }
+    if abundance
+        .density
+        .is_some_and(|density| !(0.0..=1.0).contains(&density))
+    {
+        return Err(ScenarioError::invalid(
+            format!("{}.density", field),
+            "must be between 0 and 1",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_example_matches_the_defaults_where_it_is_silent() {
+        let scenario =
+            Scenario::from_toml(include_str!("../../assets/scenarios/example.toml")).unwrap();
+        scenario.validate().unwrap();
+        assert_eq!(scenario.fleet(0).len(), 7);
+        assert_eq!(scenario.fleet(1).len(), 1);
+        assert_eq!(
+            scenario.station.target_mix,
+            Scenario::default().station.target_mix
+        );
+        assert_eq!(
+            scenario.station.costs.exploration,
+            Scenario::default().station.costs.exploration
+        );
+        assert!(Scenario::default().validate().is_ok());
+    }
+
+    #[test]
+    fn errors_name_the_bad_field() {
+        let field_of = |text: &str| match Scenario::from_toml(text).and_then(|s| s.validate()) {
+            Err(ScenarioError::Invalid { field, .. }) => field,
+            other => panic!("expected a validation error, got {:?}", other),
+        };
+
+        assert_eq!(field_of("[map]\nwidth = 4"), "map.width");
+        assert_eq!(
+            field_of("[map]\nstations = [[5, 5], [40, 0]]"),
+            "map.stations[1]"
+        );
+        assert_eq!(
+            field_of("[[robots]]\nmodules = [\"drill\"]\nstation = 1"),
+            "robots[0].station"
+        );
+        assert_eq!(
+            field_of("[[robots]]\nmodules = [\"drill\", \"drill\"]"),
+            "robots[0].modules"
+        );
+        assert_eq!(
+            field_of("[map.resources.energy]\ncount = 3\ndensity = 0.1"),
+            "map.resources.energy"
+        );
+        assert_eq!(
+            field_of("[station.build_plan.scripted]\nschedule = [[\"drill\", \"drill\"]]"),
+            "station.build_plan.scripted.schedule[0]"
+        );
+        assert_eq!(
+            field_of("[station]\nbreakdown_chance = 2.0"),
+            "station.breakdown_chance"
+        );
+        assert_eq!(
+            field_of("[station.costs.cargo_bay]\nenergy = 0\nminerals = 0\nscience = 0\nsteps = 4"),
+            "station.costs.cargo_bay"
+        );
+
+        let unknown = Scenario::from_toml("[station]\nmodule_costs = 2").unwrap_err();
+        assert!(unknown.to_string().contains("module_costs"), "{}", unknown);
+        let json = Scenario::from_json(r#"{"robots": [{"modules": ["laser"]}]}"#).unwrap_err();
+        assert!(json.to_string().contains("laser"), "{}", json);
+    }
+}
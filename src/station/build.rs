@@ -0,0 +1,470 @@
+use super::Station;
+use crate::environment::frontier::frontier_clusters;
+use crate::environment::map::CellType;
+use crate::robot::{RobotModule, MAX_PAYLOAD_WEIGHT};
+use serde::Deserialize;
+use std::fmt::{self, Debug};
+use std::ops::Add;
+use std::str::FromStr;
+
+/// Nudge given to the share of a role whose resource is running low.
+const LOW_STOCK_BONUS: f32 = 0.2;
+
+/// What fitting one module costs, in stock and in build steps.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BuildCost {
+    pub energy: u32,
+    pub minerals: u32,
+    pub science: u32,
+    pub steps: usize,
+}
+
+impl BuildCost {
+    const fn new(energy: u32, minerals: u32, science: u32, steps: usize) -> Self {
+        Self {
+            energy,
+            minerals,
+            science,
+            steps,
+        }
+    }
+
+    /// Units of stock spent, all resources together.
+    pub fn units(&self) -> u32 {
+        self.energy + self.minerals + self.science
+    }
+}
+
+impl Add for BuildCost {
+    type Output = BuildCost;
+
+    fn add(self, other: BuildCost) -> BuildCost {
+        BuildCost {
+            energy: self.energy + other.energy,
+            minerals: self.minerals + other.minerals,
+            science: self.science + other.science,
+            steps: self.steps + other.steps,
+        }
+    }
+}
+
+/// Build cost of every module; scenarios only list what they change.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(from = "CostOverrides")]
+pub struct ModuleCosts {
+    pub exploration: BuildCost,
+    pub drill: BuildCost,
+    pub energy_collector: BuildCost,
+    pub chemical_analyzer: BuildCost,
+    pub imager: BuildCost,
+    pub comms_relay: BuildCost,
+    pub cargo_bay: BuildCost,
+}
+
+impl Default for ModuleCosts {
+    fn default() -> Self {
+        Self {
+            exploration: BuildCost::new(1, 1, 1, 5),
+            drill: BuildCost::new(1, 2, 1, 8),
+            energy_collector: BuildCost::new(1, 1, 1, 5),
+            chemical_analyzer: BuildCost::new(1, 1, 2, 5),
+            imager: BuildCost::new(1, 1, 1, 3),
+            comms_relay: BuildCost::new(2, 1, 1, 6),
+            cargo_bay: BuildCost::new(1, 3, 0, 8),
+        }
+    }
+}
+
+/// A module's cost as a scenario gives it.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CostOverride {
+    energy: Option<u32>,
+    minerals: Option<u32>,
+    science: Option<u32>,
+    steps: Option<usize>,
+}
+
+impl CostOverride {
+    fn apply(self, cost: &mut BuildCost) {
+        cost.energy = self.energy.unwrap_or(cost.energy);
+        cost.minerals = self.minerals.unwrap_or(cost.minerals);
+        cost.science = self.science.unwrap_or(cost.science);
+        cost.steps = self.steps.unwrap_or(cost.steps);
+    }
+}
+
+/// The `costs` table of a scenario, laid over the stock costs.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CostOverrides {
+    exploration: CostOverride,
+    drill: CostOverride,
+    energy_collector: CostOverride,
+    chemical_analyzer: CostOverride,
+    imager: CostOverride,
+    comms_relay: CostOverride,
+    cargo_bay: CostOverride,
+}
+
+impl From<CostOverrides> for ModuleCosts {
+    fn from(overrides: CostOverrides) -> Self {
+        let mut costs = ModuleCosts::default();
+        overrides.exploration.apply(&mut costs.exploration);
+        overrides.drill.apply(&mut costs.drill);
+        overrides.energy_collector.apply(&mut costs.energy_collector);
+        overrides.chemical_analyzer.apply(&mut costs.chemical_analyzer);
+        overrides.imager.apply(&mut costs.imager);
+        overrides.comms_relay.apply(&mut costs.comms_relay);
+        overrides.cargo_bay.apply(&mut costs.cargo_bay);
+        costs
+    }
+}
+
+impl ModuleCosts {
+    pub fn of(&self, module: &RobotModule) -> BuildCost {
+        match module {
+            RobotModule::Exploration => self.exploration,
+            RobotModule::Drill => self.drill,
+            RobotModule::EnergyCollector => self.energy_collector,
+            RobotModule::ChemicalAnalyzer => self.chemical_analyzer,
+            RobotModule::Imager => self.imager,
+            RobotModule::CommsRelay => self.comms_relay,
+            RobotModule::CargoBay => self.cargo_bay,
+        }
+    }
+
+    /// Cost of a robot carrying `modules`.
+    pub fn robot(&self, modules: &[RobotModule]) -> BuildCost {
+        modules
+            .iter()
+            .map(|module| self.of(module))
+            .fold(BuildCost::default(), Add::add)
+    }
+}
+
+/// Stock the station never spends on robots.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Reserve {
+    pub energy: u32,
+    pub minerals: u32,
+    pub science: u32,
+}
+
+/// A robot the station has paid for and is putting together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildOrder {
+    pub modules: Vec<RobotModule>,
+    /// Steps of work left before the robot rolls out.
+    pub remaining: usize,
+}
+
+/// Picks the robots a station builds.
+pub trait BuildPolicy: Debug + Send {
+    /// Modules of the next robot to pay for, or none to hold off.
+    fn next_robot(&self, station: &Station) -> Vec<RobotModule>;
+}
+
+/// The build policies to choose from.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum BuildPlan {
+    #[default]
+    Heuristic,
+    FixedRatio,
+    DemandDriven,
+    Scripted {
+        schedule: Vec<Vec<RobotModule>>,
+    },
+}
+
+impl BuildPlan {
+    pub fn policy(&self) -> Box<dyn BuildPolicy> {
+        match self {
+            BuildPlan::Heuristic => Box::new(Heuristic),
+            BuildPlan::FixedRatio => Box::new(FixedRatio),
+            BuildPlan::DemandDriven => Box::new(DemandDriven),
+            BuildPlan::Scripted { schedule } => Box::new(Scripted {
+                schedule: schedule.clone(),
+            }),
+        }
+    }
+}
+
+impl FromStr for BuildPlan {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "heuristic" => Ok(BuildPlan::Heuristic),
+            "fixed-ratio" => Ok(BuildPlan::FixedRatio),
+            "demand-driven" => Ok(BuildPlan::DemandDriven),
+            "scripted" => Err("scripted schedules are set in a scenario file".to_string()),
+            _ => Err(format!(
+                "unknown build plan \"{}\", expected \"heuristic\", \"fixed-ratio\" or \"demand-driven\"",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BuildPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildPlan::Heuristic => write!(f, "heuristic"),
+            BuildPlan::FixedRatio => write!(f, "fixed-ratio"),
+            BuildPlan::DemandDriven => write!(f, "demand-driven"),
+            BuildPlan::Scripted { .. } => write!(f, "scripted"),
+        }
+    }
+}
+
+/// Shortfall of explorers, drills and energy collectors against the target mix.
+fn role_deficits(station: &Station) -> [(RobotModule, f32); 3] {
+    // A fleet of relays and haulers alone has no gatherers to share out.
+    let total = ((station.explorer_count + station.driller_count + station.energy_collector_count)
+        as f32)
+        .max(1.0);
+    let target = station.config.target_mix;
+    [
+        (
+            RobotModule::Exploration,
+            target.exploration - station.explorer_count as f32 / total,
+        ),
+        (
+            RobotModule::Drill,
+            target.drill - station.driller_count as f32 / total,
+        ),
+        (
+            RobotModule::EnergyCollector,
+            target.energy_collector - station.energy_collector_count as f32 / total,
+        ),
+    ]
+}
+
+/// The role with the largest deficit; ties go to collectors, then drills.
+fn most_needed([explorer, driller, collector]: [(RobotModule, f32); 3]) -> RobotModule {
+    if explorer.1 > driller.1 && explorer.1 > collector.1 {
+        explorer.0
+    } else if driller.1 > collector.1 {
+        driller.0
+    } else {
+        collector.0
+    }
+}
+
+/// Builds towards the target mix weighted by low stocks, adding a second module.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Heuristic;
+
+impl BuildPolicy for Heuristic {
+    fn next_robot(&self, station: &Station) -> Vec<RobotModule> {
+        if station.needs_relay() {
+            return vec![RobotModule::CommsRelay];
+        }
+
+        let primary = most_needed(role_deficits(station).map(|(module, deficit)| {
+            if station.is_stock_low(&module) {
+                (module, deficit + LOW_STOCK_BONUS)
+            } else {
+                (module, deficit)
+            }
+        }));
+        let secondary = RobotModule::ALL
+            .into_iter()
+            .find(|module| *module != primary && station.is_stock_low(module))
+            .or_else(|| station.specialist_for(&primary))
+            .filter(|module| primary.weight() + module.weight() <= MAX_PAYLOAD_WEIGHT);
+
+        let mut modules = vec![primary];
+        modules.extend(secondary);
+        // Fall back to a single-module robot when the pair is out of reach.
+        if !station.can_afford(&modules) {
+            modules.truncate(1);
+        }
+        modules
+    }
+}
+
+/// Builds single-module robots towards the target mix, whatever the stocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedRatio;
+
+impl BuildPolicy for FixedRatio {
+    fn next_robot(&self, station: &Station) -> Vec<RobotModule> {
+        if station.needs_relay() {
+            return vec![RobotModule::CommsRelay];
+        }
+        vec![most_needed(role_deficits(station))]
+    }
+}
+
+/// Builds for the role with the most known work per robot.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DemandDriven;
+
+impl BuildPolicy for DemandDriven {
+    fn next_robot(&self, station: &Station) -> Vec<RobotModule> {
+        if station.needs_relay() {
+            return vec![RobotModule::CommsRelay];
+        }
+
+        let knowledge = &station.knowledge;
+        let known = |kind: CellType| {
+            (0..knowledge.height)
+                .flat_map(|y| (0..knowledge.width).map(move |x| (x, y)))
+                .filter(|&(x, y)| knowledge.cell(x, y) == Some(kind))
+                .count()
+        };
+        let demand = [
+            (
+                RobotModule::Exploration,
+                known(CellType::ScientificSite) + frontier_clusters(knowledge).len(),
+                station.explorer_count,
+            ),
+            (
+                RobotModule::Drill,
+                known(CellType::Mineral),
+                station.driller_count,
+            ),
+            (
+                RobotModule::EnergyCollector,
+                known(CellType::Energy),
+                station.energy_collector_count,
+            ),
+        ];
+
+        let (module, _, _) = demand
+            .into_iter()
+            .reduce(|best, role| {
+                // Compares work / (robots + 1) without dividing.
+                if role.1 * (best.2 + 1) > best.1 * (role.2 + 1) {
+                    role
+                } else {
+                    best
+                }
+            })
+            .expect("three roles");
+        vec![module]
+    }
+}
+
+/// Builds the robots of `schedule` in turn, waiting for stock when short.
+#[derive(Debug, Clone, Default)]
+pub struct Scripted {
+    pub schedule: Vec<Vec<RobotModule>>,
+}
+
+impl BuildPolicy for Scripted {
+    fn next_robot(&self, station: &Station) -> Vec<RobotModule> {
+        if self.schedule.is_empty() {
+            return Vec::new();
+        }
+        self.schedule[station.orders_placed % self.schedule.len()].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::station::fixtures::station_at;
+    use crate::station::ScienceTally;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn stocked_station() -> Station {
+        let mut station = station_at(0, (25, 15));
+        station.add_energy(20);
+        station.add_minerals(20);
+        station.add_scientific_data(&ScienceTally {
+            geological: 20,
+            ..ScienceTally::default()
+        });
+        station
+    }
+
+    #[test]
+    fn demand_driven_builds_for_known_deposits() {
+        let mut station = stocked_station();
+        for x in 0..50 {
+            for y in 0..30 {
+                station.knowledge.observe(x, y, CellType::Empty, 0);
+            }
+        }
+        station.knowledge.observe(3, 3, CellType::Energy, 0);
+        assert_eq!(
+            DemandDriven.next_robot(&station),
+            vec![RobotModule::EnergyCollector]
+        );
+
+        for x in 10..15 {
+            station.knowledge.observe(x, 3, CellType::Mineral, 0);
+        }
+        assert_eq!(DemandDriven.next_robot(&station), vec![RobotModule::Drill]);
+    }
+
+    #[test]
+    fn stations_without_gatherers_build_towards_the_target_mix() {
+        let mut station = stocked_station();
+        station.enlist_fleet(&[vec![RobotModule::CommsRelay], vec![RobotModule::CargoBay]]);
+
+        assert_eq!(
+            FixedRatio.next_robot(&station),
+            vec![RobotModule::Exploration]
+        );
+        assert_eq!(Heuristic.next_robot(&station)[0], RobotModule::Exploration);
+    }
+
+    #[test]
+    fn cost_overrides_keep_the_stock_values_they_leave_out() {
+        let costs: ModuleCosts = toml::from_str("[drill]\nenergy = 4").unwrap();
+        let stock = ModuleCosts::default();
+        assert_eq!(
+            costs.drill,
+            BuildCost {
+                energy: 4,
+                ..stock.drill
+            }
+        );
+        assert_eq!(costs.imager, stock.imager);
+
+        assert!(toml::from_str::<ModuleCosts>("[drill]\nlasers = 1").is_err());
+    }
+
+    #[test]
+    fn scripted_schedules_repeat_and_wait_for_stock() {
+        let mut station = stocked_station();
+        station.minerals_storage = 5;
+        station.config.queue_length = 3;
+        station.build_policy = BuildPlan::Scripted {
+            schedule: vec![
+                vec![RobotModule::CargoBay],
+                vec![RobotModule::Exploration, RobotModule::Imager],
+            ],
+        }
+        .policy();
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..3 {
+            assert!(station.try_create_robot(5, &mut rng).is_none());
+        }
+        assert_eq!(station.build_queue().len(), 2, "short of minerals");
+
+        station.add_minerals(3);
+        station.try_create_robot(5, &mut rng);
+        let queued: Vec<_> = station
+            .build_queue()
+            .iter()
+            .map(|order| order.modules.clone())
+            .collect();
+        assert_eq!(
+            queued,
+            vec![
+                vec![RobotModule::CargoBay],
+                vec![RobotModule::Exploration, RobotModule::Imager],
+                vec![RobotModule::CargoBay],
+            ]
+        );
+    }
+}
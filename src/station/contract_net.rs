@@ -0,0 +1,256 @@
+use super::tasks::{AssignmentStrategy, Task, TaskBoard, TaskMessage};
+use crate::pathfinding;
+use crate::robot::Robot;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// Tasks a robot bothers pricing per auction, closest first, so robots do
+/// not bid on the whole board.
+const BID_SHORTLIST: usize = 3;
+
+/// A robot's offer to take on a task.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bid {
+    pub task_id: usize,
+    pub robot_id: usize,
+    /// Battery the robot expects to spend reaching the task, along the way
+    /// its own knowledge shows.
+    pub cost: u32,
+    /// Battery left once at the task and back at the base.
+    pub spare: u32,
+}
+
+/// Contract-net allocation: the station calls for bids on every open task,
+/// robots answer with what reaching it would cost them, and at the next
+/// round each task goes to the cheapest bid, the fullest battery breaking
+/// ties. A robot hears of each open task once while it looks for work, and
+/// again only if the task is released or the robot lost every bid it made.
+/// Calls, bids and awards are `TaskMessage`s.
+#[derive(Debug, Default)]
+pub struct ContractNet {
+    /// Bids received since the last round.
+    bids: Vec<Bid>,
+    /// `(robot id, task id)` pairs already called for bids.
+    announced: HashSet<(usize, usize)>,
+}
+
+impl AssignmentStrategy for ContractNet {
+    fn assign(&mut self, board: &TaskBoard, robots: &[&Robot]) -> Vec<(usize, usize)> {
+        // Bids from robots that found work since, or on tasks that went, lapse.
+        let mut bids: Vec<Bid> = std::mem::take(&mut self.bids)
+            .into_iter()
+            .filter(|bid| {
+                robots.iter().any(|r| r.id == bid.robot_id)
+                    && board.open_tasks().any(|t| t.id == bid.task_id)
+            })
+            .collect();
+        bids.sort_by_key(|b| (b.cost, Reverse(b.spare), b.robot_id, b.task_id));
+
+        let mut awarded: Vec<Bid> = Vec::new();
+        for &bid in &bids {
+            if awarded
+                .iter()
+                .all(|a| a.robot_id != bid.robot_id && a.task_id != bid.task_id)
+            {
+                awarded.push(bid);
+            }
+        }
+
+        // A robot only prices its closest tasks, so one that lost every bid
+        // is called again on all that are still open.
+        let bidders: HashSet<usize> = bids.iter().map(|b| b.robot_id).collect();
+        for robot_id in bidders {
+            if awarded.iter().all(|a| a.robot_id != robot_id) {
+                self.announced.retain(|&(r, _)| r != robot_id);
+            }
+        }
+
+        awarded
+            .into_iter()
+            .map(|bid| (bid.robot_id, bid.task_id))
+            .collect()
+    }
+
+    fn announce(&mut self, board: &TaskBoard, robots: &[&Robot]) -> Vec<TaskMessage> {
+        // Calls lapse once the robot stops looking or the task is taken.
+        self.announced.retain(|&(robot_id, task_id)| {
+            robots.iter().any(|r| r.id == robot_id) && board.open_tasks().any(|t| t.id == task_id)
+        });
+
+        let mut calls = Vec::new();
+        for robot in robots {
+            for task in board
+                .open_tasks()
+                .filter(|task| robot.has_capability(task.kind.required_capability()))
+            {
+                if self.announced.insert((robot.id, task.id)) {
+                    calls.push(TaskMessage::CallForBids {
+                        robot_id: robot.id,
+                        task: *task,
+                    });
+                }
+            }
+        }
+        calls
+    }
+
+    fn receive_bid(&mut self, bid: Bid) {
+        self.bids.push(bid);
+    }
+}
+
+/// The robot's side of the auction: prices the closest tasks it is able to
+/// do from its own knowledge, and passes on those it could not come back
+/// from or sees no way to.
+pub fn bids_for(robot: &Robot, announced: &[Task], base: (usize, usize)) -> Vec<Bid> {
+    let (base_x, base_y) = base;
+
+    let mut doable: Vec<&Task> = announced
+        .iter()
+        .filter(|task| robot.has_capability(task.kind.required_capability()))
+        .collect();
+    doable.sort_by_key(|task| (robot.x.abs_diff(task.x) + robot.y.abs_diff(task.y), task.id));
+
+    if doable.is_empty() {
+        return Vec::new();
+    }
+
+    let distances = pathfinding::distances_from(&robot.knowledge, (robot.x, robot.y));
+    doable
+        .into_iter()
+        .take(BID_SHORTLIST)
+        .filter_map(|task| {
+            let cost = distances[task.y][task.x]? * robot.move_cost();
+            let back = robot.return_trip_cost(task.x, task.y, base_x, base_y);
+            let spare = robot.battery.checked_sub(cost + back)?;
+            Some(Bid {
+                task_id: task.id,
+                robot_id: robot.id,
+                cost,
+                spare,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::map::CellType;
+    use crate::environment::KnowledgeMap;
+    use crate::station::fixtures::drill_at;
+
+    #[test]
+    fn cheapest_bid_wins_and_flat_robots_stay_out() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        knowledge.observe(22, 15, CellType::Mineral, 0);
+        let mut board = TaskBoard::default();
+        board.refresh(&knowledge, 0);
+        let task_id = board.open_tasks().next().unwrap().id;
+
+        let mut robots = [
+            drill_at(1, 28, 15),
+            drill_at(2, 23, 15),
+            drill_at(0, 24, 15),
+        ];
+        // Robot 1 has seen the way there clear; robot 0, though closer,
+        // believes it blocked and prices the detour through unknown ground.
+        for x in 20..=30 {
+            robots[0].knowledge.observe(x, 15, CellType::Empty, 0);
+        }
+        robots[2].knowledge.observe(23, 15, CellType::Obstacle, 0);
+        robots[1].battery = 10;
+
+        // First round: nothing to award yet, so every drill is called to bid,
+        // once.
+        let mut auction = ContractNet::default();
+        let looking: Vec<&Robot> = robots.iter().collect();
+        assert!(auction.assign(&board, &looking).is_empty());
+        let calls = auction.announce(&board, &looking);
+        assert_eq!(calls.len(), 3);
+        assert!(auction.announce(&board, &looking).is_empty());
+
+        for message in calls {
+            let robot_id = message.recipient().unwrap();
+            let robot = robots.iter_mut().find(|r| r.id == robot_id).unwrap();
+            robot.receive(message);
+        }
+        for robot in &mut robots {
+            let bids = robot.answer_station((25, 15));
+            assert_eq!(bids.is_empty(), robot.id == 2, "robot {}", robot.id);
+            for bid in bids {
+                auction.receive_bid(bid);
+            }
+        }
+
+        // Second round: the bids are in and the cheapest way wins.
+        let looking: Vec<&Robot> = robots.iter().collect();
+        assert_eq!(auction.assign(&board, &looking), vec![(1, task_id)]);
+
+        // Bids do not carry over to the round after.
+        assert!(auction.assign(&board, &looking).is_empty());
+
+        // A released task is put up for bids again.
+        board.claim(task_id, 1, 0);
+        let looking: Vec<&Robot> = robots.iter().filter(|r| r.id != 1).collect();
+        assert!(auction.announce(&board, &looking).is_empty());
+        board.release(task_id);
+        assert_eq!(auction.announce(&board, &looking).len(), 2);
+    }
+
+    /// Runs one round of calls and bids, then the award round after it.
+    fn auction_round(
+        auction: &mut ContractNet,
+        board: &mut TaskBoard,
+        robots: &mut [Robot],
+    ) -> Vec<(usize, usize)> {
+        let looking: Vec<&Robot> = robots.iter().filter(|r| r.task.is_none()).collect();
+        let calls = auction.announce(board, &looking);
+        for message in calls {
+            let robot_id = message.recipient().unwrap();
+            let robot = robots.iter_mut().find(|r| r.id == robot_id).unwrap();
+            robot.receive(message);
+        }
+        for robot in robots.iter_mut() {
+            for bid in robot.answer_station((25, 15)) {
+                auction.receive_bid(bid);
+            }
+        }
+
+        let looking: Vec<&Robot> = robots.iter().filter(|r| r.task.is_none()).collect();
+        let awards = auction.assign(board, &looking);
+        for &(robot_id, task_id) in &awards {
+            let task = board.claim(task_id, robot_id, 0).unwrap();
+            let robot = robots.iter_mut().find(|r| r.id == robot_id).unwrap();
+            robot.receive(TaskMessage::Award { robot_id, task });
+        }
+        awards
+    }
+
+    #[test]
+    fn robots_outbid_on_their_shortlist_bid_on_the_rest() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        for x in [10, 12, 14, 20] {
+            knowledge.observe(x, 15, CellType::Mineral, 0);
+        }
+        let mut board = TaskBoard::default();
+        board.refresh(&knowledge, 0);
+        let far_task = board.tasks().iter().find(|t| t.x == 20).unwrap().id;
+
+        // Robots 1 to 3 stand on the three deposits closest to robot 0.
+        let mut robots = vec![
+            drill_at(0, 12, 16),
+            drill_at(1, 10, 15),
+            drill_at(2, 12, 15),
+            drill_at(3, 14, 15),
+        ];
+        let mut auction = ContractNet::default();
+
+        let first = auction_round(&mut auction, &mut board, &mut robots);
+        assert_eq!(first.len(), 3);
+        assert!(first.iter().all(|&(robot_id, _)| robot_id != 0));
+
+        let second = auction_round(&mut auction, &mut board, &mut robots);
+        assert_eq!(second, vec![(0, far_task)]);
+    }
+}
@@ -0,0 +1,263 @@
+use super::Station;
+use crate::robot::{Robot, BATTERY_CAPACITY};
+use serde::Deserialize;
+
+/// Smallest load worth sending a hauler for.
+const MIN_HAUL: u32 = 5;
+
+/// What a hauler moves from one station to another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cargo {
+    Energy,
+    Minerals,
+    Science,
+}
+
+impl Cargo {
+    pub const ALL: [Cargo; 3] = [Cargo::Energy, Cargo::Minerals, Cargo::Science];
+
+    /// Units of this cargo `station` has in store.
+    pub fn stock(&self, station: &Station) -> u32 {
+        match self {
+            Cargo::Energy => station.energy_storage,
+            Cargo::Minerals => station.minerals_storage,
+            Cargo::Science => station.scientific_data_count.total(),
+        }
+    }
+
+    /// Units `station` can give away to `to` while keeping twice its
+    /// low-stock threshold for itself, or only the threshold when `to` has
+    /// none at all.
+    pub fn surplus(&self, station: &Station, to: &Station) -> u32 {
+        let threshold = station.config.low_stock_threshold;
+        let kept = if self.stock(to) == 0 {
+            threshold
+        } else {
+            2 * threshold
+        };
+        self.stock(station).saturating_sub(kept)
+    }
+}
+
+/// A transfer handed to a hauler: pick `cargo` up at station `from` and
+/// drop it at station `to`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Haul {
+    pub from: usize,
+    pub to: usize,
+    pub cargo: Cargo,
+    /// Set once the cargo is on board.
+    pub loaded: bool,
+}
+
+impl Haul {
+    /// Station the hauler is due at next.
+    pub fn destination(&self) -> usize {
+        if self.loaded {
+            self.to
+        } else {
+            self.from
+        }
+    }
+}
+
+/// Which station a robot heads back to when it unloads or recharges.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReturnTo {
+    /// The station that built it.
+    Home,
+    /// Whichever station is closest, its own winning ties.
+    #[default]
+    Nearest,
+}
+
+impl ReturnTo {
+    /// Index of the station `robot` heads back to. Haulers on a run head for
+    /// the station they are due at instead.
+    pub fn base_for(&self, robot: &Robot, stations: &[Station]) -> usize {
+        if let Some(haul) = robot.haul {
+            return haul.destination();
+        }
+
+        match self {
+            ReturnTo::Home => robot.home,
+            ReturnTo::Nearest => stations
+                .iter()
+                .min_by_key(|station| {
+                    let (x, y) = station.position();
+                    (
+                        robot.x.abs_diff(x) + robot.y.abs_diff(y),
+                        station.id != robot.home,
+                    )
+                })
+                .map_or(robot.home, |station| station.id),
+        }
+    }
+}
+
+/// Hands every idle hauler a run to a station short of some resource, from
+/// the station with the most of it to spare. A hauler only takes runs it can
+/// reach the source of on its current charge, stations too far apart for a
+/// full battery are never paired, and no two haulers serve the same need.
+pub fn dispatch_haulers(stations: &[Station], robots: &mut [Robot]) {
+    for i in 0..robots.len() {
+        if robots[i].is_broken() {
+            // Whatever it carried is lost; let another hauler take over.
+            robots[i].haul = None;
+        }
+
+        let robot = &robots[i];
+        if !robot.is_hauler()
+            || robot.haul.is_some()
+            || robot.cargo_load() > 0
+            || robot.is_broken()
+            || robot.is_stranded()
+        {
+            continue;
+        }
+
+        let served: Vec<Haul> = robots.iter().filter_map(|r| r.haul).collect();
+        robots[i].haul = next_haul(&robots[i], stations, &served);
+    }
+}
+
+fn next_haul(hauler: &Robot, stations: &[Station], served: &[Haul]) -> Option<Haul> {
+    for cargo in Cargo::ALL {
+        let mut short: Vec<&Station> = stations
+            .iter()
+            .filter(|s| cargo.stock(s) < s.config.low_stock_threshold)
+            .filter(|s| !served.iter().any(|h| h.to == s.id && h.cargo == cargo))
+            .collect();
+        short.sort_by_key(|s| (cargo.stock(s), s.id));
+
+        for to in short {
+            let donor = stations
+                .iter()
+                .filter(|from| from.id != to.id && cargo.surplus(from, to) >= MIN_HAUL)
+                .filter(|from| {
+                    let ((fx, fy), (tx, ty)) = (from.position(), to.position());
+                    hauler.return_trip_cost(hauler.x, hauler.y, fx, fy) <= hauler.battery
+                        && hauler.return_trip_cost(fx, fy, tx, ty) <= BATTERY_CAPACITY
+                })
+                .max_by_key(|from| (cargo.surplus(from, to), std::cmp::Reverse(from.id)));
+            if let Some(from) = donor {
+                return Some(Haul {
+                    from: from.id,
+                    to: to.id,
+                    cargo,
+                    loaded: false,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Loads or clears the run of a hauler at station `at`, once it has
+/// unloaded there. Before loading, the hauler tops its battery up for the
+/// whole leg, even if that wastes part of an energy unit. A run whose source
+/// cannot fuel it or has nothing left to give is dropped.
+pub fn exchange(hauler: &mut Robot, stations: &mut [Station], at: usize) {
+    let Some(mut haul) = hauler.haul else {
+        return;
+    };
+    let (x, y) = stations[at].position();
+    if !hauler.is_near_base(x, y) || hauler.is_broken() {
+        return;
+    }
+
+    if haul.loaded && haul.to == at {
+        hauler.haul = None;
+    } else if !haul.loaded && haul.from == at {
+        let (to_x, to_y) = stations[haul.to].position();
+        let surplus = haul.cargo.surplus(&stations[at], &stations[haul.to]);
+        let station = &mut stations[at];
+        let leg = hauler.return_trip_cost(x, y, to_x, to_y);
+        hauler.battery +=
+            station.draw_battery(leg.min(BATTERY_CAPACITY).saturating_sub(hauler.battery));
+
+        let room = hauler.cargo_capacity.saturating_sub(hauler.cargo_load());
+        let units = room.min(surplus);
+        if hauler.battery < leg || units == 0 {
+            hauler.haul = None;
+            return;
+        }
+
+        match haul.cargo {
+            Cargo::Energy => hauler.carried_energy += station.draw_energy(units),
+            Cargo::Minerals => hauler.carried_minerals += station.draw_minerals(units),
+            Cargo::Science => {
+                let samples = station.scientific_data_count.take(units);
+                hauler.carried_scientific_data.add_tally(&samples);
+            }
+        }
+        haul.loaded = true;
+        hauler.haul = Some(haul);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::KnowledgeMap;
+    use crate::robot::RobotModule;
+    use crate::station::fixtures::station_at;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn haulers_carry_surplus_to_stations_running_short() {
+        let mut stations = vec![station_at(0, (10, 15)), station_at(1, (40, 15))];
+        stations[0].add_energy(30);
+        stations[1].add_energy(30);
+        stations[1].add_minerals(30);
+
+        let mut robots = vec![Robot::new(
+            7,
+            10,
+            15,
+            vec![RobotModule::CargoBay],
+            KnowledgeMap::new(50, 30),
+            ChaCha8Rng::seed_from_u64(0),
+        )];
+        dispatch_haulers(&stations, &mut robots);
+        let mut hauler = robots.pop().unwrap();
+
+        let haul = hauler.haul.expect("station 0 has no minerals");
+        assert_eq!((haul.from, haul.to, haul.cargo), (1, 0, Cargo::Minerals));
+        assert_eq!(ReturnTo::Nearest.base_for(&hauler, &stations), 1);
+
+        exchange(&mut hauler, &mut stations, 0);
+        assert!(!hauler.haul.unwrap().loaded, "loads at the source only");
+
+        hauler.x = 40;
+        exchange(&mut hauler, &mut stations, 1);
+        assert_eq!(hauler.carried_minerals, 10);
+        assert_eq!(stations[1].minerals_storage, 20);
+        assert_eq!(ReturnTo::Home.base_for(&hauler, &stations), 0);
+
+        hauler.x = 10;
+        hauler.try_deposit_resources(&mut stations[0], 0);
+        exchange(&mut hauler, &mut stations, 0);
+        assert_eq!(stations[0].minerals_storage, 10);
+        assert_eq!(hauler.haul, None);
+    }
+
+    #[test]
+    fn stations_give_more_to_those_with_nothing() {
+        let mut stations = [station_at(0, (10, 15)), station_at(1, (40, 15))];
+        stations[1].add_energy(12);
+
+        let threshold = stations[1].config.low_stock_threshold;
+        assert_eq!(
+            Cargo::Energy.surplus(&stations[1], &stations[0]),
+            12 - threshold
+        );
+        stations[0].add_energy(1);
+        assert_eq!(
+            Cargo::Energy.surplus(&stations[1], &stations[0]),
+            12 - 2 * threshold
+        );
+    }
+}
@@ -1,26 +1,216 @@
-use crate::robot::{Robot, RobotModule};
+pub mod build;
+pub mod contract_net;
+pub mod logistics;
+pub mod tasks;
+
+use self::build::{BuildOrder, BuildPlan, BuildPolicy, Heuristic, ModuleCosts, Reserve};
+use self::tasks::{Allocation, AssignmentStrategy, GreedyNearest, TaskBoard, TaskMessage};
+use crate::environment::knowledge::MergeConflict;
+use crate::environment::map::SiteKind;
+use crate::environment::{KnowledgeCommit, KnowledgeMap, Map, MapConfig};
+use crate::robot::{CargoCapacities, Robot, RobotModule, BATTERY_PER_ENERGY_UNIT};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+/// Scientific data broken down by the kind of site it was sampled from.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ScienceTally {
+    pub geological: u32,
+    pub chemical: u32,
+    pub biosignature: u32,
+}
+
+impl ScienceTally {
+    pub fn get(&self, kind: SiteKind) -> u32 {
+        match kind {
+            SiteKind::Geological => self.geological,
+            SiteKind::Chemical => self.chemical,
+            SiteKind::Biosignature => self.biosignature,
+        }
+    }
+
+    fn get_mut(&mut self, kind: SiteKind) -> &mut u32 {
+        match kind {
+            SiteKind::Geological => &mut self.geological,
+            SiteKind::Chemical => &mut self.chemical,
+            SiteKind::Biosignature => &mut self.biosignature,
+        }
+    }
+
+    pub fn add(&mut self, kind: SiteKind, amount: u32) {
+        *self.get_mut(kind) += amount;
+    }
+
+    pub fn add_tally(&mut self, other: &ScienceTally) {
+        for kind in SiteKind::ALL {
+            self.add(kind, other.get(kind));
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.geological + self.chemical + self.biosignature
+    }
+
+    /// Removes `amount` samples, always drawing from the largest stock.
+    /// Returns false, leaving the tally untouched, if there are not enough.
+    pub fn spend(&mut self, amount: u32) -> bool {
+        if self.total() < amount {
+            return false;
+        }
+
+        self.take(amount);
+        true
+    }
+
+    /// Removes up to `amount` samples, drawing from the largest stock, and
+    /// returns those taken.
+    pub fn take(&mut self, amount: u32) -> ScienceTally {
+        let mut taken = ScienceTally::default();
+        for _ in 0..amount.min(self.total()) {
+            let kind = SiteKind::ALL
+                .into_iter()
+                .max_by_key(|&kind| self.get(kind))
+                .unwrap_or(SiteKind::Geological);
+            *self.get_mut(kind) -= 1;
+            taken.add(kind, 1);
+        }
+        taken
+    }
+}
+
+/// Stock below which the station favours robots that gather that resource.
+const LOW_STOCK_THRESHOLD: u32 = 5;
+/// Robots the station has paid for at most, the one being built included.
+const QUEUE_LENGTH: usize = 2;
+/// The station keeps one comms relay per this many robots built.
+const RELAY_FLEET_SHARE: usize = 8;
+/// Share of the map the station must know before explorers are fitted with
+/// chemical analysers rather than imagers.
+const IMAGING_COVERAGE_TARGET: f32 = 0.5;
+
+/// Share of explorers, miners and energy collectors the station builds
+/// towards.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FleetMix {
+    pub exploration: f32,
+    pub drill: f32,
+    pub energy_collector: f32,
+}
+
+impl Default for FleetMix {
+    fn default() -> Self {
+        Self {
+            exploration: 0.4,
+            drill: 0.3,
+            energy_collector: 0.3,
+        }
+    }
+}
+
+/// What robots cost the station and how it picks the next one.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StationConfig {
+    pub costs: ModuleCosts,
+    /// Hold each module gives the robots the station builds.
+    pub cargo: CargoCapacities,
+    pub reserve: Reserve,
+    /// Robots the station has paid for at most, the one being built
+    /// included.
+    pub queue_length: usize,
+    /// How the station picks the robots it builds.
+    pub build_plan: BuildPlan,
+    /// How open tasks are matched with robots.
+    pub allocation: Allocation,
+    pub low_stock_threshold: u32,
+    pub relay_fleet_share: usize,
+    pub target_mix: FleetMix,
+    /// Odds that a robot breaks down for good on any one move. Off unless a
+    /// scenario sets it.
+    pub breakdown_chance: f64,
+}
+
+impl Default for StationConfig {
+    fn default() -> Self {
+        Self {
+            costs: ModuleCosts::default(),
+            cargo: CargoCapacities::default(),
+            reserve: Reserve::default(),
+            queue_length: QUEUE_LENGTH,
+            build_plan: BuildPlan::default(),
+            allocation: Allocation::default(),
+            low_stock_threshold: LOW_STOCK_THRESHOLD,
+            relay_fleet_share: RELAY_FLEET_SHARE,
+            target_mix: FleetMix::default(),
+            breakdown_chance: 0.0,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Station {
+    /// Index of the station in the simulation.
+    pub id: usize,
+    /// Cell the station stands on. Robots are built, unload and recharge
+    /// around it.
+    position: (usize, usize),
     pub energy_storage: u32,
+    /// Battery left over from energy units already drawn, always less than
+    /// one unit, handed out before another unit is drawn.
+    battery_credit: u32,
     pub minerals_storage: u32,
-    pub scientific_data_count: u32,
+    pub scientific_data_count: ScienceTally,
+    /// Master map, assembled from what robots bring back.
+    pub knowledge: KnowledgeMap,
     robot_counter: usize,
+    /// Robots still working that recharge here, as last counted by the
+    /// simulation.
+    live_robots: usize,
+    /// Every knowledge merge, oldest first. A commit's id is its index.
+    history: Vec<KnowledgeCommit>,
     explorer_count: usize,
     driller_count: usize,
     energy_collector_count: usize,
+    relay_count: usize,
+    /// Robots paid for, the one being built first.
+    queue: VecDeque<BuildOrder>,
+    /// Robots paid for since the start of the run.
+    orders_placed: usize,
+    /// Picks the robots to pay for.
+    pub build_policy: Box<dyn BuildPolicy>,
+    pub tasks: TaskBoard,
+    /// How open tasks are matched with robots.
+    pub assignment: Box<dyn AssignmentStrategy>,
+    pub config: StationConfig,
 }
 
 impl Station {
-    pub fn new() -> Self {
+    /// Station `id`, standing at `position` on a map shaped like `config`.
+    pub fn new(id: usize, config: &MapConfig, position: (usize, usize)) -> Self {
         Self {
+            id,
+            position,
             energy_storage: 0,
+            battery_credit: 0,
             minerals_storage: 0,
-            scientific_data_count: 0,
+            scientific_data_count: ScienceTally::default(),
+            knowledge: KnowledgeMap::new(config.width, config.height),
             robot_counter: 5,
+            live_robots: 5,
+            history: Vec::new(),
             explorer_count: 2,
             driller_count: 2,
             energy_collector_count: 1,
+            relay_count: 0,
+            queue: VecDeque::new(),
+            orders_placed: 0,
+            build_policy: Box::new(Heuristic),
+            tasks: TaskBoard::default(),
+            assignment: Box::new(GreedyNearest),
+            config: StationConfig::default(),
         }
     }
 
@@ -28,12 +218,144 @@ impl Station {
         self.energy_storage += amount;
     }
 
+    /// Takes up to `units` of stored energy and returns how much was taken.
+    pub fn draw_energy(&mut self, units: u32) -> u32 {
+        let drawn = units.min(self.energy_storage);
+        self.energy_storage -= drawn;
+        drawn
+    }
+
+    /// Hands out up to `points` of battery, drawing whole energy units only
+    /// when the credit left from earlier draws falls short. Returns the
+    /// battery given.
+    pub fn draw_battery(&mut self, points: u32) -> u32 {
+        let short = points.saturating_sub(self.battery_credit);
+        let units = self.draw_energy(short.div_ceil(BATTERY_PER_ENERGY_UNIT));
+        let available = self.battery_credit + units * BATTERY_PER_ENERGY_UNIT;
+        let given = points.min(available);
+        self.battery_credit = available - given;
+        given
+    }
+
     pub fn add_minerals(&mut self, amount: u32) {
         self.minerals_storage += amount;
     }
 
-    pub fn add_scientific_data(&mut self, amount: u32) {
-        self.scientific_data_count += amount;
+    /// Takes up to `units` of stored minerals and returns how much was taken.
+    pub fn draw_minerals(&mut self, units: u32) -> u32 {
+        let drawn = units.min(self.minerals_storage);
+        self.minerals_storage -= drawn;
+        drawn
+    }
+
+    pub fn add_scientific_data(&mut self, samples: &ScienceTally) {
+        self.scientific_data_count.add_tally(samples);
+    }
+
+    /// Records what the station's own sensors see around it.
+    pub fn observe(&mut self, map: &Map, radius: i32, step: usize) {
+        self.knowledge
+            .observe_area(map, self.position.0, self.position.1, radius, step);
+    }
+
+    /// Merges a robot's knowledge into the master map and records the merge
+    /// as a commit in the station's history.
+    pub fn sync_knowledge(
+        &mut self,
+        robot_id: usize,
+        robot_knowledge: &KnowledgeMap,
+        step: usize,
+    ) -> KnowledgeCommit {
+        let outcome = self.knowledge.merge(robot_knowledge);
+        let commit = KnowledgeCommit {
+            id: self.history.len(),
+            robot_id,
+            step,
+            outcome,
+        };
+        self.history.push(commit.clone());
+        commit
+    }
+
+    pub fn commit_history(&self) -> &[KnowledgeCommit] {
+        &self.history
+    }
+
+    /// Commits pushed by `robot_id`, oldest first.
+    pub fn commits_by_robot(&self, robot_id: usize) -> impl Iterator<Item = &KnowledgeCommit> {
+        self.history.iter().filter(move |c| c.robot_id == robot_id)
+    }
+
+    /// Every conflict ever settled, with the commit that settled it.
+    pub fn conflict_log(&self) -> impl Iterator<Item = (&KnowledgeCommit, &MergeConflict)> {
+        self.history
+            .iter()
+            .flat_map(|commit| commit.outcome.conflicts.iter().map(move |c| (commit, c)))
+    }
+
+    /// Updates the task board from the master map, settles the tasks robots
+    /// hold, and hands open tasks to the robots looking for work. Only the
+    /// robots based at this station, haulers aside, take part. Returns the
+    /// messages for robots: the awards, then whatever the assignment strategy
+    /// has to say to the robots still looking.
+    pub fn coordinate_tasks(&mut self, robots: &mut [Robot], step: usize) -> Vec<TaskMessage> {
+        self.tasks.refresh(&self.knowledge, step);
+
+        for robot in robots.iter_mut().filter(|r| r.home == self.id) {
+            let Some(task) = robot.task else {
+                continue;
+            };
+
+            if task.is_done(&robot.knowledge) {
+                self.tasks.complete(task.id, step);
+                robot.task = None;
+            } else if robot.is_broken() || robot.is_stranded() {
+                self.tasks.release(task.id);
+                robot.task = None;
+            } else if !self.tasks.is_claimed_by(task.id, robot.id) {
+                // The claim lapsed, or the work vanished from the board.
+                robot.task = None;
+            }
+        }
+
+        let (base_x, base_y) = self.position;
+        let mut looking: Vec<&Robot> = robots
+            .iter()
+            .filter(|r| {
+                r.home == self.id
+                    && !r.is_hauler()
+                    && r.task.is_none()
+                    && !r.is_broken()
+                    && !r.is_stranded()
+                    && !r.is_cargo_full()
+                    && !r.needs_recharge(base_x, base_y)
+            })
+            .collect();
+
+        let mut messages = Vec::new();
+        for (robot_id, task_id) in self.assignment.assign(&self.tasks, &looking) {
+            if let Some(task) = self.tasks.claim(task_id, robot_id, step) {
+                messages.push(TaskMessage::Award { robot_id, task });
+            }
+        }
+
+        looking.retain(|r| messages.iter().all(|m| m.recipient() != Some(r.id)));
+        messages.extend(self.assignment.announce(&self.tasks, &looking));
+        messages
+    }
+
+    /// Takes stock of the robots a run starts with, in place of the default
+    /// fleet `new` assumes.
+    pub fn enlist_fleet(&mut self, fleet: &[Vec<RobotModule>]) {
+        self.robot_counter = fleet.len();
+        self.live_robots = fleet.len();
+        self.explorer_count = 0;
+        self.driller_count = 0;
+        self.energy_collector_count = 0;
+        self.relay_count = 0;
+        for module in fleet.iter().flatten() {
+            self.update_robot_counts(module);
+        }
     }
 
     pub fn update_robot_counts(&mut self, robot_type: &RobotModule) {
@@ -41,93 +363,281 @@ impl Station {
             RobotModule::Exploration => self.explorer_count += 1,
             RobotModule::Drill => self.driller_count += 1,
             RobotModule::EnergyCollector => self.energy_collector_count += 1,
+            RobotModule::CommsRelay => self.relay_count += 1,
+            // Specialist add-ons and haulers do not count towards the role
+            // ratios.
+            RobotModule::ChemicalAnalyzer | RobotModule::Imager | RobotModule::CargoBay => {}
         }
     }
 
-    pub fn try_create_robot(&mut self) -> Option<Robot> {
-        let min_resources_needed = 1;
+    /// Pays for the next robot if the stocks allow it above the reserve,
+    /// then works one step on the robot at the head of the queue. Returns
+    /// that robot, numbered `id`, once it is finished.
+    pub fn try_create_robot(&mut self, id: usize, rng: &mut impl Rng) -> Option<Robot> {
+        if self.queue.len() < self.config.queue_length {
+            self.queue_next_robot();
+        }
 
-        if self.energy_storage >= min_resources_needed
-            && self.minerals_storage >= min_resources_needed
-            && self.scientific_data_count >= min_resources_needed
-        {
-            let robot_module = self.determine_next_robot_type();
+        let order = self.queue.front_mut()?;
+        order.remaining = order.remaining.saturating_sub(1);
+        if order.remaining > 0 {
+            return None;
+        }
 
-            let resource_cost = match &robot_module {
-                RobotModule::Exploration => 1,
-                RobotModule::Drill => 1,
-                RobotModule::EnergyCollector => 1,
-            };
+        let order = self.queue.pop_front()?;
+        let mut robot = Robot::new(
+            id,
+            self.position.0,
+            self.position.1,
+            order.modules,
+            self.knowledge.clone(),
+            ChaCha8Rng::seed_from_u64(rng.gen()),
+        );
+        robot.home = self.id;
+        robot.cargo_capacity = self.config.cargo.robot(&robot.modules);
+        robot.breakdown_chance = self.config.breakdown_chance;
+        Some(robot)
+    }
 
-            self.energy_storage -= resource_cost;
-            self.minerals_storage -= resource_cost;
-            self.scientific_data_count -= resource_cost;
+    /// Robots paid for, the one being built first.
+    pub fn build_queue(&self) -> &VecDeque<BuildOrder> {
+        &self.queue
+    }
 
-            let robot = Robot::new(
-                self.robot_counter,
-                self.get_center_x(),
-                self.get_center_y(),
-                vec![robot_module.clone()],
-            );
+    /// Orders the robot the build policy picks and pays for it up front.
+    fn queue_next_robot(&mut self) {
+        let modules = self.build_policy.next_robot(self);
+        if modules.is_empty() || !self.can_afford(&modules) {
+            return;
+        }
 
-            self.robot_counter += 1;
-            self.update_robot_counts(&robot_module);
+        let cost = self.config.costs.robot(&modules);
+        self.energy_storage -= cost.energy;
+        self.minerals_storage -= cost.minerals;
+        self.scientific_data_count.spend(cost.science);
 
-            Some(robot)
-        } else {
-            None
+        for module in &modules {
+            self.update_robot_counts(module);
         }
+        self.robot_counter += 1;
+        self.orders_placed += 1;
+
+        self.queue.push_back(BuildOrder {
+            modules,
+            remaining: cost.steps,
+        });
+    }
+
+    /// Whether a robot carrying `modules` can be paid for without dipping
+    /// into the reserve or the energy kept for recharges.
+    fn can_afford(&self, modules: &[RobotModule]) -> bool {
+        let cost = self.config.costs.robot(modules);
+        let reserve = self.config.reserve;
+        self.energy_storage >= cost.energy + reserve.energy + self.recharge_reserve()
+            && self.minerals_storage >= cost.minerals + reserve.minerals
+            && self.scientific_data_count.total() >= cost.science + reserve.science
     }
 
-    fn determine_next_robot_type(&self) -> RobotModule {
-        let total_robots = self.explorer_count + self.driller_count + self.energy_collector_count;
+    /// Energy units builds leave for recharges: one per live robot the
+    /// station recharges or has paid for, so a build never strands the
+    /// robots waiting at the station.
+    pub fn recharge_reserve(&self) -> u32 {
+        (self.live_robots + self.queue.len()) as u32
+    }
 
-        let explorer_percent = self.explorer_count as f32 / total_robots as f32;
-        let driller_percent = self.driller_count as f32 / total_robots as f32;
-        let energy_collector_percent = self.energy_collector_count as f32 / total_robots as f32;
+    /// Sets how many robots, neither broken nor stranded out of reach,
+    /// recharge at the station.
+    pub fn set_live_robots(&mut self, count: usize) {
+        self.live_robots = count;
+    }
 
-        const TARGET_EXPLORER_PERCENT: f32 = 0.4;
-        const TARGET_DRILLER_PERCENT: f32 = 0.3;
-        const TARGET_ENERGY_PERCENT: f32 = 0.3;
+    /// Whether the fleet has outgrown its comms relays.
+    fn needs_relay(&self) -> bool {
+        self.robot_counter / self.config.relay_fleet_share.max(1) > self.relay_count
+    }
 
-        let explorer_deficit = TARGET_EXPLORER_PERCENT - explorer_percent;
-        let driller_deficit = TARGET_DRILLER_PERCENT - driller_percent;
-        let energy_deficit = TARGET_ENERGY_PERCENT - energy_collector_percent;
+    /// Specialist module worth fitting next to `primary`: imagers while the
+    /// map is mostly unknown, chemical analysers once it is charted.
+    fn specialist_for(&self, primary: &RobotModule) -> Option<RobotModule> {
+        match primary {
+            RobotModule::Exploration => {
+                if self.knowledge.known_fraction() < IMAGING_COVERAGE_TARGET {
+                    Some(RobotModule::Imager)
+                } else {
+                    Some(RobotModule::ChemicalAnalyzer)
+                }
+            }
+            _ => None,
+        }
+    }
 
-        let resource_adjusted_driller_deficit = if self.minerals_storage < 5 {
-            driller_deficit + 0.2
-        } else {
-            driller_deficit
+    /// Whether the resource `module` brings back is running low.
+    fn is_stock_low(&self, module: &RobotModule) -> bool {
+        let stock = match module {
+            RobotModule::Exploration => self.scientific_data_count.total(),
+            RobotModule::Drill => self.minerals_storage,
+            RobotModule::EnergyCollector => self.energy_storage,
+            RobotModule::ChemicalAnalyzer
+            | RobotModule::Imager
+            | RobotModule::CommsRelay
+            | RobotModule::CargoBay => return false,
         };
+        stock < self.config.low_stock_threshold
+    }
 
-        let resource_adjusted_energy_deficit = if self.energy_storage < 5 {
-            energy_deficit + 0.2
-        } else {
-            energy_deficit
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+}
+
+/// Stations and robots the station's tests share, on a 50 by 30 map.
+#[cfg(test)]
+mod fixtures {
+    use super::Station;
+    use crate::environment::{KnowledgeMap, MapConfig};
+    use crate::robot::{Robot, RobotModule};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    pub fn station_at(id: usize, position: (usize, usize)) -> Station {
+        let config = MapConfig {
+            width: 50,
+            height: 30,
+            seed: 0,
         };
+        Station::new(id, &config, position)
+    }
+
+    /// A drill robot at (`x`, `y`) that has seen nothing yet.
+    pub fn drill_at(id: usize, x: usize, y: usize) -> Robot {
+        Robot::new(
+            id,
+            x,
+            y,
+            vec![RobotModule::Drill],
+            KnowledgeMap::new(50, 30),
+            ChaCha8Rng::seed_from_u64(0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::station_at;
+    use super::*;
+    use crate::environment::map::CellType;
 
-        let resource_adjusted_explorer_deficit = if self.scientific_data_count < 5 {
-            explorer_deficit + 0.2
-        } else {
-            explorer_deficit
+    #[test]
+    fn sync_history_records_every_commit() {
+        let config = MapConfig {
+            width: 4,
+            height: 1,
+            seed: 0,
         };
+        let mut station = Station::new(0, &config, config.centre());
+        station.knowledge.observe(0, 0, CellType::Empty, 5);
+
+        let mut first = KnowledgeMap::new(4, 1);
+        first.observe(0, 0, CellType::Mineral, 1);
+        first.observe(1, 0, CellType::Obstacle, 1);
+        let mut second = KnowledgeMap::new(4, 1);
+        second.observe(2, 0, CellType::Energy, 7);
+
+        station.sync_knowledge(3, &first, 10);
+        station.sync_knowledge(4, &second, 12);
+
+        let history = station.commit_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            (history[0].id, history[0].robot_id, history[0].step),
+            (0, 3, 10)
+        );
+        assert_eq!(
+            (history[0].outcome.added, history[0].outcome.conflicts.len()),
+            (1, 1)
+        );
+        assert_eq!(
+            (history[1].id, history[1].robot_id, history[1].outcome.added),
+            (1, 4, 1)
+        );
+        assert_eq!(station.commits_by_robot(4).count(), 1);
+        assert_eq!(station.conflict_log().count(), 1);
+    }
+
+    #[test]
+    fn low_stocks_lead_to_multi_module_builds() {
+        let mut station = station_at(0, (25, 15));
+        station.add_energy(10);
+        station.add_minerals(3);
+        let mut samples = ScienceTally::default();
+        samples.add(SiteKind::Chemical, 10);
+        station.add_scientific_data(&samples);
 
-        if resource_adjusted_explorer_deficit > resource_adjusted_driller_deficit
-            && resource_adjusted_explorer_deficit > resource_adjusted_energy_deficit
-        {
-            RobotModule::Exploration
-        } else if resource_adjusted_driller_deficit > resource_adjusted_energy_deficit {
-            RobotModule::Drill
-        } else {
-            RobotModule::EnergyCollector
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert!(station.try_create_robot(5, &mut rng).is_none());
+        let order = &station.build_queue()[0];
+        assert_eq!(
+            order.modules,
+            vec![RobotModule::EnergyCollector, RobotModule::Drill]
+        );
+        assert_eq!(station.energy_storage, 8);
+        assert_eq!(station.minerals_storage, 0);
+        assert_eq!(station.scientific_data_count.total(), 8);
+        assert_eq!(station.build_queue().len(), 1, "no minerals left");
+
+        let steps = station.config.costs.robot(&order.modules).steps;
+        for _ in 2..steps {
+            assert!(station.try_create_robot(5, &mut rng).is_none());
         }
+        let robot = station.try_create_robot(5, &mut rng).unwrap();
+        assert_eq!(robot.modules.len(), 2);
+        assert!(station.build_queue().is_empty());
     }
 
-    fn get_center_x(&self) -> usize {
-        50 / 2
+    #[test]
+    fn builds_leave_the_reserve_untouched() {
+        let mut station = station_at(0, (25, 15));
+        station.config.reserve.energy = 10;
+        let kept = 10 + station.recharge_reserve();
+        assert_eq!(station.recharge_reserve(), 5, "a unit per robot");
+        station.add_energy(kept);
+        station.add_minerals(10);
+        let mut samples = ScienceTally::default();
+        samples.add(SiteKind::Geological, 10);
+        station.add_scientific_data(&samples);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert!(station.try_create_robot(5, &mut rng).is_none());
+        assert!(station.build_queue().is_empty());
+
+        station.add_energy(1);
+        station.try_create_robot(5, &mut rng);
+        assert_eq!(station.build_queue()[0].modules.len(), 1);
+        assert_eq!(station.energy_storage, kept);
+        assert_eq!(station.recharge_reserve(), 6, "the new robot counts too");
+
+        station.set_live_robots(2);
+        assert_eq!(station.recharge_reserve(), 3, "lost robots do not");
     }
 
-    fn get_center_y(&self) -> usize {
-        30 / 2
+    #[test]
+    fn fleet_growth_triggers_relay_builds() {
+        let mut station = station_at(0, (25, 15));
+        assert_ne!(
+            Heuristic.next_robot(&station),
+            vec![RobotModule::CommsRelay]
+        );
+
+        station.robot_counter = RELAY_FLEET_SHARE;
+        assert_eq!(
+            Heuristic.next_robot(&station),
+            vec![RobotModule::CommsRelay]
+        );
+
+        station.update_robot_counts(&RobotModule::CommsRelay);
+        assert_ne!(
+            Heuristic.next_robot(&station),
+            vec![RobotModule::CommsRelay]
+        );
     }
 }
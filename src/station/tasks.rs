@@ -0,0 +1,356 @@
+use super::contract_net::{Bid, ContractNet};
+use crate::environment::frontier::{frontier_clusters, is_frontier};
+use crate::environment::map::{CellType, SiteKind};
+use crate::environment::KnowledgeMap;
+use crate::robot::{Capability, Robot};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+
+/// Ticks a claim holds before the task goes back on the board.
+const CLAIM_TTL: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskKind {
+    Mine,
+    HarvestEnergy,
+    Sample(SiteKind),
+    /// Reveal the unknown ground around a frontier cluster.
+    Explore,
+}
+
+impl TaskKind {
+    pub fn required_capability(&self) -> Capability {
+        match self {
+            TaskKind::Mine => Capability::Mine,
+            TaskKind::HarvestEnergy => Capability::HarvestEnergy,
+            TaskKind::Sample(kind) => Capability::Sample(*kind),
+            TaskKind::Explore => Capability::Explore,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Claim {
+    pub robot_id: usize,
+    pub expires_at: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Task {
+    pub id: usize,
+    pub kind: TaskKind,
+    pub x: usize,
+    pub y: usize,
+    pub claim: Option<Claim>,
+}
+
+impl Task {
+    /// Whether `knowledge` shows the task no longer needs doing.
+    pub fn is_done(&self, knowledge: &KnowledgeMap) -> bool {
+        match self.kind {
+            TaskKind::Mine => knowledge.cell(self.x, self.y) != Some(CellType::Mineral),
+            TaskKind::HarvestEnergy => knowledge.cell(self.x, self.y) != Some(CellType::Energy),
+            TaskKind::Sample(_) => knowledge.cell(self.x, self.y) != Some(CellType::ScientificSite),
+            TaskKind::Explore => !is_frontier(knowledge, self.x, self.y),
+        }
+    }
+}
+
+/// What a station and its robots tell each other about tasks. Messages go
+/// over the simulation's event channel, as they would over the radio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskMessage {
+    /// The station asks a robot looking for work to price a task.
+    CallForBids { robot_id: usize, task: Task },
+    /// A robot's offer to take on a task, for the station.
+    Bid(Bid),
+    /// The station hands a task to a robot, its claim already on the board.
+    Award { robot_id: usize, task: Task },
+}
+
+impl TaskMessage {
+    /// Robot the message is for, or nothing when it is for the station.
+    pub fn recipient(&self) -> Option<usize> {
+        match self {
+            TaskMessage::CallForBids { robot_id, .. } | TaskMessage::Award { robot_id, .. } => {
+                Some(*robot_id)
+            }
+            TaskMessage::Bid(_) => None,
+        }
+    }
+}
+
+/// Picks which robot takes which open task.
+pub trait AssignmentStrategy: Debug + Send {
+    /// Returns `(robot id, task id)` pairs to award, using each robot and
+    /// each task at most once. `robots` only holds robots looking for work.
+    fn assign(&mut self, board: &TaskBoard, robots: &[&Robot]) -> Vec<(usize, usize)>;
+
+    /// Messages to send once the awards are out, to the robots still looking
+    /// for work. Only strategies that negotiate have any.
+    fn announce(&mut self, _board: &TaskBoard, _robots: &[&Robot]) -> Vec<TaskMessage> {
+        Vec::new()
+    }
+
+    /// Takes in a bid a robot sent back.
+    fn receive_bid(&mut self, _bid: Bid) {}
+}
+
+/// The assignment strategies to choose from.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Allocation {
+    #[default]
+    Greedy,
+    ContractNet,
+}
+
+impl Allocation {
+    pub fn strategy(&self) -> Box<dyn AssignmentStrategy> {
+        match self {
+            Allocation::Greedy => Box::new(GreedyNearest),
+            Allocation::ContractNet => Box::new(ContractNet::default()),
+        }
+    }
+}
+
+impl FromStr for Allocation {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "greedy" => Ok(Allocation::Greedy),
+            "contract-net" => Ok(Allocation::ContractNet),
+            _ => Err(format!(
+                "unknown allocation \"{}\", expected \"greedy\" or \"contract-net\"",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Allocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Allocation::Greedy => write!(f, "greedy"),
+            Allocation::ContractNet => write!(f, "contract-net"),
+        }
+    }
+}
+
+/// Hands out the closest robot-task pairs first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreedyNearest;
+
+impl AssignmentStrategy for GreedyNearest {
+    fn assign(&mut self, board: &TaskBoard, robots: &[&Robot]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for robot in robots {
+            for task in board.open_tasks() {
+                if robot.has_capability(task.kind.required_capability()) {
+                    let distance = robot.x.abs_diff(task.x) + robot.y.abs_diff(task.y);
+                    pairs.push((distance, robot.id, task.id));
+                }
+            }
+        }
+        pairs.sort_unstable();
+
+        let mut assignments: Vec<(usize, usize)> = Vec::new();
+        for (_, robot_id, task_id) in pairs {
+            if assignments
+                .iter()
+                .all(|&(r, t)| r != robot_id && t != task_id)
+            {
+                assignments.push((robot_id, task_id));
+            }
+        }
+        assignments
+    }
+}
+
+/// Work the station knows of: deposits to drain, sites to sample and
+/// frontiers to push back. Robots claim a task to keep others off it.
+#[derive(Debug, Default)]
+pub struct TaskBoard {
+    tasks: Vec<Task>,
+    next_id: usize,
+    /// Step at which a task was completed at a cell. The station's map lags
+    /// behind robots, so the cell is not published again until the station
+    /// has a newer observation of it.
+    retired: HashMap<(usize, usize), usize>,
+}
+
+impl TaskBoard {
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    pub fn get(&self, task_id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.id == task_id)
+    }
+
+    pub fn open_tasks(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().filter(|t| t.claim.is_none())
+    }
+
+    /// Cells claimed by robots, with the claiming robot.
+    pub fn claimed_cells(&self) -> Vec<((usize, usize), usize)> {
+        self.tasks
+            .iter()
+            .filter_map(|t| t.claim.map(|c| ((t.x, t.y), c.robot_id)))
+            .collect()
+    }
+
+    /// Brings the board in line with the station's map: publishes newly
+    /// known work, drops work that is no longer there and lets stale claims
+    /// lapse.
+    pub fn refresh(&mut self, knowledge: &KnowledgeMap, step: usize) {
+        self.tasks.retain(|task| !task.is_done(knowledge));
+
+        for task in &mut self.tasks {
+            if task.claim.is_some_and(|c| c.expires_at <= step) {
+                task.claim = None;
+            }
+        }
+
+        for y in 0..knowledge.height {
+            for x in 0..knowledge.width {
+                let kind = match knowledge.cell(x, y) {
+                    Some(CellType::Mineral) => TaskKind::Mine,
+                    Some(CellType::Energy) => TaskKind::HarvestEnergy,
+                    Some(CellType::ScientificSite) => match knowledge.site_kind(x, y) {
+                        Some(kind) => TaskKind::Sample(kind),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                self.publish(kind, x, y, knowledge);
+            }
+        }
+
+        // One exploration task per frontier cluster, so explorers split up.
+        for cluster in frontier_clusters(knowledge) {
+            let covered = self
+                .tasks
+                .iter()
+                .any(|t| t.kind == TaskKind::Explore && cluster.contains(t.x, t.y));
+            if !covered {
+                let (x, y) = cluster.target;
+                self.publish(TaskKind::Explore, x, y, knowledge);
+            }
+        }
+    }
+
+    /// Adds a task at (`x`, `y`) unless one is there already, or the cell
+    /// was retired and the station has not seen it since.
+    fn publish(&mut self, kind: TaskKind, x: usize, y: usize, knowledge: &KnowledgeMap) {
+        let stale = knowledge.get(x, y).is_none_or(|observation| {
+            self.retired
+                .get(&(x, y))
+                .is_some_and(|&at| observation.observed_at <= at)
+        });
+        if stale || self.tasks.iter().any(|t| (t.x, t.y) == (x, y)) {
+            return;
+        }
+
+        self.tasks.push(Task {
+            id: self.next_id,
+            kind,
+            x,
+            y,
+            claim: None,
+        });
+        self.next_id += 1;
+    }
+
+    /// Reserves `task_id` for `robot_id`. Fails if someone else holds it.
+    pub fn claim(&mut self, task_id: usize, robot_id: usize, step: usize) -> Option<Task> {
+        let task = self.tasks.iter_mut().find(|t| t.id == task_id)?;
+        if task.claim.is_some_and(|c| c.robot_id != robot_id) {
+            return None;
+        }
+
+        task.claim = Some(Claim {
+            robot_id,
+            expires_at: step + CLAIM_TTL,
+        });
+        Some(*task)
+    }
+
+    /// Puts `task_id` back on the board for others.
+    pub fn release(&mut self, task_id: usize) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id) {
+            task.claim = None;
+        }
+    }
+
+    /// Takes `task_id` off the board for good.
+    pub fn complete(&mut self, task_id: usize, step: usize) {
+        if let Some(index) = self.tasks.iter().position(|t| t.id == task_id) {
+            let task = self.tasks.remove(index);
+            self.retired.insert((task.x, task.y), step);
+        }
+    }
+
+    /// Whether `robot_id` still holds `task_id`.
+    pub fn is_claimed_by(&self, task_id: usize, robot_id: usize) -> bool {
+        self.get(task_id)
+            .and_then(|t| t.claim)
+            .is_some_and(|c| c.robot_id == robot_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::station::fixtures::drill_at;
+
+    #[test]
+    fn drills_are_sent_to_different_deposits() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        knowledge.observe(10, 10, CellType::Mineral, 0);
+        knowledge.observe(20, 10, CellType::Mineral, 0);
+
+        let mut board = TaskBoard::default();
+        board.refresh(&knowledge, 0);
+        let mine_tasks = board.tasks().iter().filter(|t| t.kind == TaskKind::Mine);
+        assert_eq!(mine_tasks.count(), 2);
+
+        let near = drill_at(0, 11, 10);
+        let also_near = drill_at(1, 12, 10);
+        let assignments = GreedyNearest.assign(&board, &[&near, &also_near]);
+
+        let target = |robot_id| {
+            let (_, task_id) = assignments.iter().find(|(r, _)| *r == robot_id).unwrap();
+            let task = board.get(*task_id).unwrap();
+            (task.x, task.y)
+        };
+        assert_eq!(target(0), (10, 10));
+        assert_eq!(target(1), (20, 10));
+    }
+
+    #[test]
+    fn claims_expire_and_finished_work_is_not_republished() {
+        let mut knowledge = KnowledgeMap::new(50, 30);
+        knowledge.observe(10, 10, CellType::Mineral, 0);
+
+        let mut board = TaskBoard::default();
+        board.refresh(&knowledge, 0);
+        let task_id = board.tasks()[0].id;
+
+        assert!(board.claim(task_id, 7, 0).is_some());
+        assert!(board.claim(task_id, 8, 1).is_none(), "already claimed");
+        board.refresh(&knowledge, CLAIM_TTL);
+        assert!(!board.is_claimed_by(task_id, 7));
+
+        board.complete(task_id, 5);
+        board.refresh(&knowledge, 6);
+        assert!(board.tasks().is_empty(), "station map is stale");
+
+        knowledge.observe(10, 10, CellType::Mineral, 9);
+        board.refresh(&knowledge, 10);
+        assert_eq!(board.tasks().len(), 1);
+    }
+}
@@ -13,8 +13,10 @@ use ratatui::{
 };
 use std::io;
 
-use crate::environment::map::{CellType, CellVisibility};
-use crate::robot::RobotModule;
+use crate::environment::knowledge::ConflictResolution;
+use crate::environment::map::{CellType, CellVisibility, MAX_DEPOSIT};
+use crate::environment::KnowledgeCommit;
+use crate::robot::{RobotModule, BATTERY_CAPACITY};
 use crate::simulation::Simulation;
 
 #[derive(Clone)]
@@ -31,14 +33,50 @@ impl CellDisplay {
         }
     }
 
-    fn to_styled_string(&self) -> String {
+    fn to_span(&self) -> Span<'static> {
         match self {
-            CellDisplay::Char(c, style) => Span::styled(c.to_string(), *style).to_string(),
-            CellDisplay::Str(s, style) => Span::styled(s.to_string(), *style).to_string(),
+            CellDisplay::Char(c, style) => Span::styled(c.to_string(), *style),
+            CellDisplay::Str(s, style) => Span::styled(*s, *style),
         }
     }
 }
 
+/// Background for a deposit cell: `rgb` at full strength for a full deposit,
+/// fading as it is mined out.
+fn deposit_shade((r, g, b): (u8, u8, u8), amount: u32) -> Color {
+    let fill = amount.min(MAX_DEPOSIT) as f64 / MAX_DEPOSIT as f64;
+    let intensity = 0.25 + 0.75 * fill;
+    Color::Rgb(
+        (r as f64 * intensity) as u8,
+        (g as f64 * intensity) as u8,
+        (b as f64 * intensity) as u8,
+    )
+}
+
+fn module_glyph(module: &RobotModule) -> (&'static str, Color) {
+    match module {
+        RobotModule::Exploration => ("🔍", Color::Indexed(86)),
+        RobotModule::Drill => ("⛏️", Color::Indexed(214)),
+        RobotModule::EnergyCollector => ("🔋", Color::Indexed(118)),
+        RobotModule::ChemicalAnalyzer => ("🧪", Color::Indexed(171)),
+        RobotModule::Imager => ("📷", Color::Indexed(45)),
+        RobotModule::CommsRelay => ("📡", Color::Indexed(230)),
+        RobotModule::CargoBay => ("📦", Color::Indexed(180)),
+    }
+}
+
+fn module_label(module: &RobotModule) -> &'static str {
+    match module {
+        RobotModule::Exploration => "Explorer",
+        RobotModule::Drill => "Miner",
+        RobotModule::EnergyCollector => "Energy",
+        RobotModule::ChemicalAnalyzer => "Chemist",
+        RobotModule::Imager => "Imager",
+        RobotModule::CommsRelay => "Relay",
+        RobotModule::CargoBay => "Hauler",
+    }
+}
+
 pub struct Ui {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
 }
@@ -55,12 +93,17 @@ impl Ui {
     }
 
     pub fn draw(&mut self, simulation: &Simulation) -> Result<(), io::Error> {
+        let science = simulation.stored_science();
         let status_text = format!(
-            "Energy: {} | Minerals: {} | Data: {} | Robots: {} | Step: {}",
-            simulation.station.energy_storage,
-            simulation.station.minerals_storage,
-            simulation.station.scientific_data_count,
+            "Energy: {} | Minerals: {} | Data: geo {} chem {} bio {} | Stations: {} | Robots: {} | Explored: {:.0}% | Step: {}",
+            simulation.stored_energy(),
+            simulation.stored_minerals(),
+            science.geological,
+            science.chemical,
+            science.biosignature,
+            simulation.stations.len(),
             simulation.robots.len(),
+            simulation.stats.explored_percent,
             simulation.stats.simulation_step
         );
 
@@ -113,6 +156,7 @@ impl Ui {
 
                     for y in 0..simulation.map.config.height {
                         for x in 0..simulation.map.config.width {
+                            let amount = simulation.map.amounts[y][x];
                             let cell_display = match simulation.map.visibility[y][x] {
                                 CellVisibility::Hidden => {
                                     CellDisplay::Str("▒▒", Style::default().fg(Color::Rgb(30, 30, 50)).bg(Color::Rgb(10, 10, 20)))
@@ -121,8 +165,8 @@ impl Ui {
                                     match simulation.map.cells[y][x] {
                                         CellType::Empty => CellDisplay::Char(' ', Style::default()),  
                                         CellType::Obstacle => CellDisplay::Str("🏔️", Style::default().fg(Color::Rgb(80, 80, 80))), 
-                                        CellType::Energy => CellDisplay::Char('⚡', Style::default().fg(Color::Rgb(80, 80, 0))),
-                                        CellType::Mineral => CellDisplay::Char('💎', Style::default().fg(Color::Rgb(20, 50, 50))),
+                                        CellType::Energy => CellDisplay::Char('⚡', Style::default().fg(Color::Rgb(80, 80, 0)).bg(deposit_shade((40, 40, 0), amount))),
+                                        CellType::Mineral => CellDisplay::Char('💎', Style::default().fg(Color::Rgb(20, 50, 50)).bg(deposit_shade((10, 35, 35), amount))),
                                         CellType::ScientificSite => CellDisplay::Char('🔬', Style::default().fg(Color::Rgb(80, 40, 80))),
                                     }
                                 }
@@ -130,8 +174,8 @@ impl Ui {
                                     match simulation.map.cells[y][x] {
                                         CellType::Empty => CellDisplay::Char(' ', Style::default()), 
                                         CellType::Obstacle => CellDisplay::Str("🏔️", Style::default().fg(Color::Rgb(160, 120, 90))),
-                                        CellType::Energy => CellDisplay::Char('⚡', Style::default().fg(Color::Indexed(226)).add_modifier(Modifier::BOLD)),
-                                        CellType::Mineral => CellDisplay::Char('💎', Style::default().fg(Color::Indexed(51)).add_modifier(Modifier::BOLD)),
+                                        CellType::Energy => CellDisplay::Char('⚡', Style::default().fg(Color::Indexed(226)).bg(deposit_shade((90, 90, 0), amount)).add_modifier(Modifier::BOLD)),
+                                        CellType::Mineral => CellDisplay::Char('💎', Style::default().fg(Color::Indexed(51)).bg(deposit_shade((0, 80, 80), amount)).add_modifier(Modifier::BOLD)),
                                         CellType::ScientificSite => CellDisplay::Char('🔬', Style::default().fg(Color::Indexed(201)).add_modifier(Modifier::BOLD)),
                                     }
                                 }
@@ -144,45 +188,46 @@ impl Ui {
                                 ctx.print(
                                     pos_x,
                                     pos_y,
-                                    cell_display.to_styled_string(),
+                                    cell_display.to_span(),
                                 );
                             }
                         }
                     }
 
-                    let center_x = offset_x + ((simulation.map.config.width / 2) as f64 * cell_spacing_x);
-                    let center_y = offset_y + ((simulation.map.config.height / 2) as f64 * cell_spacing_y);
-
-                    ctx.print(
-                        center_x,
-                        center_y,
-                        Span::styled(
-                            "🏠",
-                            Style::default()
-                                .fg(Color::Indexed(231))
-                                .add_modifier(Modifier::BOLD),
-                        )
-                        .to_string(),
-                    );
+                    for station in &simulation.stations {
+                        let (base_x, base_y) = station.position();
+                        let station_x = offset_x + (base_x as f64 * cell_spacing_x);
+                        let station_y = offset_y + (base_y as f64 * cell_spacing_y);
+
+                        ctx.print(
+                            station_x,
+                            station_y,
+                            Span::styled(
+                                "🏠",
+                                Style::default()
+                                    .fg(Color::Indexed(231))
+                                    .add_modifier(Modifier::BOLD),
+                            ),
+                        );
+                    }
 
                     for robot in &simulation.robots {
                         let scaled_x = offset_x + (robot.x as f64 * cell_spacing_x);
                         let scaled_y = offset_y + (robot.y as f64 * cell_spacing_y);
 
-                        let (robot_char, robot_color) =
-                            if robot.modules.contains(&RobotModule::Exploration) {
-                                ("🔍", Color::Indexed(86)) 
-                            } else if robot.modules.contains(&RobotModule::Drill) {
-                                ("⛏️", Color::Indexed(214))
-                            } else if robot.modules.contains(&RobotModule::EnergyCollector) {
-                                ("🔋", Color::Indexed(118)) 
-                            } else {
-                                ("🤖", Color::Indexed(250)) 
-                            };
-
-                        let carrying = robot.carried_energy > 0
-                            || robot.carried_minerals > 0
-                            || robot.carried_scientific_data > 0;
+                        // Robots are drawn after their primary (first) module.
+                        let (robot_char, robot_color) = robot
+                            .modules
+                            .first()
+                            .map(module_glyph)
+                            .unwrap_or(("🤖", Color::Indexed(250)));
+
+                        let carrying = robot.cargo_load() > 0;
+                        let robot_color = if robot.is_stranded() || robot.is_broken() {
+                            Color::DarkGray
+                        } else {
+                            robot_color
+                        };
                         let robot_style =
                             Style::default().fg(robot_color).add_modifier(if carrying {
                                 Modifier::BOLD
@@ -193,7 +238,7 @@ impl Ui {
                         ctx.print(
                             scaled_x,
                             scaled_y,
-                            Span::styled(robot_char, robot_style).to_string(),
+                            Span::styled(robot_char, robot_style),
                         );
                     }
                 })
@@ -206,8 +251,9 @@ impl Ui {
                 .direction(Direction::Vertical)
                 .constraints(
                     [
-                        Constraint::Length(7),
-                        Constraint::Length(3),
+                        Constraint::Length(11),
+                        Constraint::Length(6),
+                        Constraint::Length(8),
                         Constraint::Min(0),
                     ]
                     .as_ref(),
@@ -234,6 +280,22 @@ impl Ui {
                     Span::styled("🔋 ", Style::default().fg(Color::Indexed(118))),
                     Span::raw("Energy Collector"),
                 ]),
+                Line::from(vec![
+                    Span::styled("🧪 ", Style::default().fg(Color::Indexed(171))),
+                    Span::raw("Chemical Analyser"),
+                ]),
+                Line::from(vec![
+                    Span::styled("📷 ", Style::default().fg(Color::Indexed(45))),
+                    Span::raw("Imager"),
+                ]),
+                Line::from(vec![
+                    Span::styled("📡 ", Style::default().fg(Color::Indexed(230))),
+                    Span::raw("Comms Relay"),
+                ]),
+                Line::from(vec![
+                    Span::styled("📦 ", Style::default().fg(Color::Indexed(180))),
+                    Span::raw("Hauler"),
+                ]),
                 Line::from(vec![
                     Span::styled("⚡ ", Style::default().fg(Color::Indexed(226))),
                     Span::raw("Energy"),
@@ -250,12 +312,29 @@ impl Ui {
 
             frame.render_widget(legend_widget, details_layout[0]);
 
-            let stats_text = format!(
+            let mut stats_lines = vec![Line::from(format!(
                 "Energy: {} | Minerals: {} | Science: {}",
                 simulation.stats.total_energy_collected,
                 simulation.stats.total_minerals_collected,
                 simulation.stats.total_scientific_data_collected,
-            );
+            ))];
+            for (icon, metrics) in [
+                ("⚡", &simulation.stats.energy),
+                ("💎", &simulation.stats.minerals),
+                ("🔬", &simulation.stats.science),
+            ] {
+                let first = metrics
+                    .first_discovery
+                    .map_or("-".to_string(), |step| format!("@{}", step));
+                stats_lines.push(Line::from(format!(
+                    "{} found {:.0}% taken {:.0}% first {}",
+                    icon,
+                    metrics.discovered_percent(),
+                    metrics.harvested_percent(),
+                    first
+                )));
+            }
+            let stats_text = Text::from(stats_lines);
 
             let stats_block = Paragraph::new(stats_text).block(
                 Block::default()
@@ -268,19 +347,88 @@ impl Ui {
 
             frame.render_widget(stats_block, details_layout[1]);
 
+            // Commits of every station, oldest first.
+            let mut history: Vec<(usize, &KnowledgeCommit)> = simulation
+                .stations
+                .iter()
+                .flat_map(|s| s.commit_history().iter().map(move |c| (s.id, c)))
+                .collect();
+            history.sort_by_key(|(station, commit)| (commit.step, *station));
+            let conflict_total: usize = simulation
+                .stations
+                .iter()
+                .map(|s| s.conflict_log().count())
+                .sum();
+            let several_stations = simulation.stations.len() > 1;
+            let max_visible_commits = (details_layout[2].height as usize).saturating_sub(3);
+
+            let mut sync_items = vec![ListItem::new(format!(
+                "Commits: {} | Conflicts: {}",
+                history.len(),
+                conflict_total
+            ))];
+
+            for (station, commit) in history.iter().rev().take(max_visible_commits) {
+                let resolutions: Vec<String> = ConflictResolution::ALL
+                    .iter()
+                    .filter_map(|&resolution| {
+                        let count = commit.outcome.resolution_count(resolution);
+                        (count > 0).then(|| format!("{} {}", count, resolution.label()))
+                    })
+                    .collect();
+
+                let label = if several_stations {
+                    format!("S{}#{}", station, commit.id)
+                } else {
+                    format!("#{}", commit.id)
+                };
+                sync_items.push(ListItem::new(format!(
+                    "{} R{} @{} +{} ~{} !{} {}",
+                    label,
+                    commit.robot_id,
+                    commit.step,
+                    commit.outcome.added,
+                    commit.outcome.updated,
+                    commit.outcome.conflicts.len(),
+                    resolutions.join(", ")
+                )));
+            }
+
+            let sync_list = List::new(sync_items).block(
+                Block::default()
+                    .title(Span::styled(
+                        "Knowledge Sync",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            );
+
+            frame.render_widget(sync_list, details_layout[2]);
+
             let mut robot_items = Vec::new();
             let mut explorer_count = 0;
             let mut miner_count = 0;
             let mut energy_count = 0;
+            let mut relay_count = 0;
+            let mut hauler_count = 0;
 
+            // A multi-module robot counts towards every role it can fill.
             for robot in &simulation.robots {
                 if robot.modules.contains(&RobotModule::Exploration) {
                     explorer_count += 1;
-                } else if robot.modules.contains(&RobotModule::Drill) {
+                }
+                if robot.modules.contains(&RobotModule::Drill) {
                     miner_count += 1;
-                } else if robot.modules.contains(&RobotModule::EnergyCollector) {
+                }
+                if robot.modules.contains(&RobotModule::EnergyCollector) {
                     energy_count += 1;
                 }
+                if robot.modules.contains(&RobotModule::CommsRelay) {
+                    relay_count += 1;
+                }
+                if robot.is_hauler() {
+                    hauler_count += 1;
+                }
             }
 
             robot_items.push(ListItem::new(format!("Explorers: {}", explorer_count)));
@@ -289,13 +437,67 @@ impl Ui {
                 "Energy Collectors: {}",
                 energy_count
             )));
+            robot_items.push(ListItem::new(format!(
+                "Relays: {} | Haulers: {}",
+                relay_count, hauler_count
+            )));
+
+            let tasks: Vec<_> = simulation
+                .stations
+                .iter()
+                .flat_map(|s| s.tasks.tasks())
+                .collect();
+            let claimed_tasks = tasks.iter().filter(|t| t.claim.is_some()).count();
+            robot_items.push(ListItem::new(format!(
+                "Tasks: {} open, {} claimed",
+                tasks.len() - claimed_tasks,
+                claimed_tasks
+            )));
+
+            for station in &simulation.stations {
+                let label = if several_stations {
+                    format!("Build S{}", station.id)
+                } else {
+                    "Build".to_string()
+                };
+                let queue = station.build_queue();
+                let line = match queue.front() {
+                    Some(order) => format!(
+                        "{}: {} in {} | {} waiting",
+                        label,
+                        order
+                            .modules
+                            .iter()
+                            .map(module_label)
+                            .collect::<Vec<_>>()
+                            .join("+"),
+                        order.remaining,
+                        queue.len() - 1
+                    ),
+                    None => format!("{}: idle", label),
+                };
+                robot_items.push(ListItem::new(line));
+            }
+
+            let average_trip = simulation
+                .stats
+                .average_trip_length()
+                .map_or("-".to_string(), |ticks| format!("{:.1}", ticks));
+            robot_items.push(ListItem::new(format!(
+                "Moved: {} | Idle: {} | Avg trip: {}",
+                simulation.stats.distance_travelled(),
+                simulation.stats.idle_ticks(),
+                average_trip
+            )));
 
             if !simulation.robots.is_empty() {
                 robot_items.push(ListItem::new(""));
                 robot_items.push(ListItem::new("Active robots:"));
 
-                let max_visible_robots = if details_layout[2].height > 10 {
-                    (details_layout[2].height as usize - 6).min(simulation.robots.len())
+                let max_visible_robots = if details_layout[3].height > 10 {
+                    (details_layout[3].height as usize)
+                        .saturating_sub(robot_items.len())
+                        .min(simulation.robots.len())
                 } else {
                     3.min(simulation.robots.len())
                 };
@@ -306,29 +508,56 @@ impl Ui {
                     .enumerate()
                     .take(max_visible_robots)
                 {
-                    let robot_type = if robot.modules.contains(&RobotModule::Exploration) {
-                        "Explorer"
-                    } else if robot.modules.contains(&RobotModule::Drill) {
-                        "Miner"
-                    } else if robot.modules.contains(&RobotModule::EnergyCollector) {
-                        "Energy"
+                    let robot_type = if robot.modules.is_empty() {
+                        "Unknown".to_string()
                     } else {
-                        "Unknown"
+                        robot
+                            .modules
+                            .iter()
+                            .map(module_label)
+                            .collect::<Vec<_>>()
+                            .join("+")
                     };
 
-                    let carrying = if robot.carried_energy > 0 {
-                        format!("⚡{}", robot.carried_energy)
-                    } else if robot.carried_minerals > 0 {
-                        format!("💎{}", robot.carried_minerals)
-                    } else if robot.carried_scientific_data > 0 {
-                        format!("🔬{}", robot.carried_scientific_data)
-                    } else {
-                        "".to_string()
-                    };
+                    // Total load against the hold, then what it is made of.
+                    let breakdown: Vec<String> = [
+                        ("⚡", robot.carried_energy),
+                        ("💎", robot.carried_minerals),
+                        ("🔬", robot.carried_scientific_data.total()),
+                    ]
+                    .into_iter()
+                    .filter(|&(_, amount)| amount > 0)
+                    .map(|(glyph, amount)| format!("{}{}", glyph, amount))
+                    .collect();
+                    let mut carrying = format!("{}/{}", robot.cargo_load(), robot.cargo_capacity);
+                    if !breakdown.is_empty() {
+                        carrying = format!("{} {}", carrying, breakdown.join(" "));
+                    }
+
+                    let syncs: usize = simulation
+                        .stations
+                        .iter()
+                        .map(|s| s.commits_by_robot(robot.id).count())
+                        .sum();
+
+                    let battery = robot.battery * 100 / BATTERY_CAPACITY;
+
+                    let travelled = simulation
+                        .stats
+                        .robots
+                        .iter()
+                        .find(|m| m.robot_id == robot.id)
+                        .map_or(0, |m| m.distance_travelled);
 
                     robot_items.push(ListItem::new(format!(
-                        "#{}: {} {}",
-                        robot.id, robot_type, carrying
+                        "#{}: {} {} ⇄{} {} {}% ↦{}",
+                        robot.id,
+                        robot_type,
+                        carrying,
+                        syncs,
+                        robot.state.label(),
+                        battery,
+                        travelled
                     )));
                 }
 
@@ -349,7 +578,7 @@ impl Ui {
                     .borders(Borders::ALL),
             );
 
-            frame.render_widget(robot_list, details_layout[2]);
+            frame.render_widget(robot_list, details_layout[3]);
         })?;
 
         Ok(())